@@ -2,6 +2,7 @@ pub mod options;
 
 mod senders;
 mod limiter;
+mod sessions;
 mod query;
 
 #[cfg(test)]
@@ -11,13 +12,18 @@ mod limiter_test;
 
 use std::clone::Clone;
 use std::ops::Not;
+use std::sync::{Arc, RwLock};
 use anyhow::anyhow;
 use derive_more::From;
 use regex::Regex;
 use once_cell::sync::Lazy;
 use rust_i18n::t;
 use crate::{help, metrics};
-use crate::loc::{finder, google, osm, yandex, Location, SearchChain};
+use crate::loc::{routing, Location};
+use crate::loc::nearby::Category;
+use crate::loc::suggest::with_session_token;
+use crate::loc::config::SearchChainConfig;
+use crate::loc::providers::{FinderChains, Providers};
 use crate::utils::{ensure_lang_code, try_determine_location};
 use teloxide::prelude::*;
 use teloxide::dispatching::dialogue::GetChatId;
@@ -25,8 +31,9 @@ use teloxide::types::{Me, ReplyMarkup};
 use teloxide::types::ParseMode::{Html, MarkdownV2};
 use teloxide::utils::command::BotCommands;
 use crate::handlers::limiter::RequestsLimiter;
+use crate::handlers::sessions::InlineSessions;
 use crate::handlers::options::LanguageCode;
-use crate::handlers::query::{QueryCheckMode, QUERY_CHECK_MODE};
+use crate::handlers::query::{categories, codes, coords, links, QueryCheckMode, QUERY_CHECK_MODE};
 use crate::redis::REDIS;
 use crate::users::{UserService, UserServiceClient, UserServiceClientGrpc};
 
@@ -45,37 +52,43 @@ pub enum Command {
 
 pub type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
-static COORDS_REGEXP: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?P<latitude>-?\d{1,2}([.,]\d+)?),?\s+(?P<longitude>-?\d{1,3}([.,]\d+)?)$")
-    .expect("Invalid coords regex!"));
 static QUERY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\pL(\pM)?){3,}"#)
     .expect("Invalid query regex!"));
-static FINDER: Lazy<SearchChain> = Lazy::new(|| {
-    let osm = finder("OSM", osm::OpenStreetMapLocFinder::new());
-    let yandex = finder("YANDEX", yandex::YandexLocFinder::from_env());
-    let google = finder("GOOGLE", google::GoogleLocFinder::from_env());
-
-    SearchChain::new(vec![
-        google.clone(),
-        osm.clone(),
-        yandex.clone(),
-    ]).for_lang_code("ru", vec![
-        yandex,
-        google,
-        osm,
-    ])
+static PROVIDERS: Lazy<Providers> = Lazy::new(Providers::from_env);
+static FINDERS: Lazy<RwLock<Arc<FinderChains>>> = Lazy::new(|| {
+    let chains = SearchChainConfig::load()
+        .map(|config| PROVIDERS.chains(&config))
+        .unwrap_or_else(|err| panic!("Invalid search chain config: {err}"));
+    RwLock::new(Arc::new(chains))
 });
 static INLINE_REQUESTS_LIMITER: Lazy<RequestsLimiter> = Lazy::new(|| RequestsLimiter::from_env(&REDIS.pool));
+static SESSIONS: Lazy<InlineSessions> = Lazy::new(|| InlineSessions::from_env(&REDIS.pool));
 
 pub fn preload_env_vars() {
-    google::preload_env_vars();
-    yandex::preload_env_vars();
-
     query::preload_env_vars();
+    routing::preload_boundaries();
 
-    let _ = *COORDS_REGEXP;
     let _ = *QUERY_REGEX;
-    let _ = *FINDER;
+    let _ = *FINDERS;
     let _ = *INLINE_REQUESTS_LIMITER;
+    let _ = *SESSIONS;
+}
+
+/// Re-read the search chain config. The current chains are kept if the new config is invalid.
+pub fn reload_finders() {
+    let result = SearchChainConfig::load()
+        .map(|config| PROVIDERS.chains(&config));
+    match result {
+        Ok(chains) => {
+            *FINDERS.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(chains);
+            tracing::info!("The search chain config has been reloaded");
+        }
+        Err(err) => tracing::error!("couldn't reload the search chain config, the current one is kept: {err}"),
+    }
+}
+
+fn finders() -> Arc<FinderChains> {
+    FINDERS.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
 }
 
 #[tracing::instrument(skip(bot, usr_client), fields(query = %q.query))]
@@ -89,16 +102,23 @@ pub async fn inline_handler(bot: Bot, q: InlineQuery, usr_client: UserService<Us
     metrics::INLINE_COUNTER.inc_allowed();
 
     let lang_code = &ensure_lang_code(q.from.id, q.from.language_code.clone(), &usr_client).await;
-    let location = try_determine_location(q.from.id, &usr_client).await;
-    let locations = resolve_locations(q.query, lang_code, location).await?;
+    // the location sent along with the query is fresher than the saved one
+    let location = match &q.location {
+        Some(location) => Some((location.latitude, location.longitude)),
+        None => try_determine_location(q.from.id, &usr_client).await,
+    };
+    let session_token = SESSIONS.token(q.from.id).await;
+    let locations = with_session_token(session_token, resolve_locations(q.query, lang_code, location, &finders())).await?;
 
-    senders::send_locations_inline(bot, q.id, lang_code, locations).await
+    senders::send_locations_inline(bot, q.id, lang_code, locations, &SESSIONS).await
 }
 
 fn is_query_correct(query: &str) -> bool {
     let allowed = query.is_empty().not() && (
         QUERY_REGEX.is_match(query)   ||
-            COORDS_REGEXP.is_match(query) ||
+            coords::parse(query).is_some() ||
+            codes::parse(query).is_some() ||
+            links::parse(query).is_some() ||
             *QUERY_CHECK_MODE != QueryCheckMode::Regex
     );
     if !allowed {
@@ -117,9 +137,24 @@ async fn rate_limit_exceeded(q: &InlineQuery) -> bool {
     forbidden
 }
 
-pub async fn inline_chosen_handler(_: Bot, _: ChosenInlineResult) -> HandlerResult {
+/// Resolve the chosen suggestion and put its address into the sent message.
+pub async fn inline_chosen_handler(bot: Bot, chosen: ChosenInlineResult, usr_client: UserService<UserServiceClientGrpc>) -> HandlerResult {
     metrics::INLINE_CHOSEN_COUNTER.inc();
-    Ok(())
+    // only the results of suggestions have a keyboard, so Telegram passes the message only for them
+    let Some(inline_message_id) = chosen.inline_message_id else {
+        return Ok(())
+    };
+    let Some(suggestion) = SESSIONS.suggestion(&chosen.result_id).await else {
+        tracing::warn!("the chosen suggestion {} has expired", chosen.result_id);
+        return Ok(())
+    };
+
+    let lang_code = &ensure_lang_code(chosen.from.id, chosen.from.language_code.clone(), &usr_client).await;
+    let session_token = SESSIONS.end(chosen.from.id).await;
+    match with_session_token(session_token, PROVIDERS.resolve(&suggestion, lang_code)).await {
+        Some(location) => senders::complete_inline_suggestion(bot, inline_message_id, &suggestion, &location, lang_code).await,
+        None => Ok(())
+    }
 }
 
 #[derive(From)]
@@ -181,7 +216,25 @@ pub async fn callback_handler(bot: Bot, q: CallbackQuery) -> HandlerResult {
         q.data.clone().unwrap_or("<null>".to_string()));
 
     let mut answer = bot.answer_callback_query(q.id.clone());
-    if let (Some(chat_id), Some(data)) = (q.chat_id(), q.data) {
+    if let (Some(chat_id), Some(id)) = (q.chat_id(), q.data.as_deref().and_then(|data| data.strip_prefix(senders::SUGGESTION_CALLBACK_PREFIX))) {
+        let lang_code = q.from.language_code.unwrap_or_default();
+        let location = match SESSIONS.suggestion(id).await {
+            Some(suggestion) => {
+                let session_token = SESSIONS.end(q.from.id).await;
+                with_session_token(session_token, PROVIDERS.resolve(&suggestion, &lang_code)).await
+            }
+            None => None
+        };
+        match location {
+            Some(location) => {
+                bot.send_location(chat_id, location.latitude(), location.longitude()).await?;
+            }
+            None => {
+                answer.text = Some(t!("error.suggestion", locale = &lang_code).to_string());
+                answer.show_alert = Some(true);
+            }
+        }
+    } else if let (Some(chat_id), Some(data)) = (q.chat_id(), q.data) {
         let parts: Vec<&str> = data.split(',').collect();
         if parts.len() != 2 {
             Err("unexpected format of callback data")?;
@@ -203,34 +256,118 @@ async fn cmd_loc_handler(bot: Bot, msg: Message, usr_client: UserService<impl Us
     let from = msg.from.as_ref().ok_or("no from")?;
     let lang_code = &ensure_lang_code(from.id, from.language_code.clone(), &usr_client).await;
 
-    let text = match msg.text() {
-        None => return send_error(bot, msg, "error.query.empty", lang_code).await,
-        Some(text) => text.to_string()
+    let locations = match (msg.text(), msg.location()) {
+        (Some(text), _) => {
+            tracing::info!("Got a message query: {}", text);
+            let location = try_determine_location(from.id, &usr_client).await;
+            let session_token = SESSIONS.token(from.id).await;
+            let locations = with_session_token(session_token, resolve_locations(text.to_string(), lang_code, location, &finders())).await?;
+            resolve_single_suggestion(locations, from.id, lang_code).await
+        }
+        (None, Some(shared)) => {
+            tracing::info!("Got a shared location: {}, {}", shared.latitude, shared.longitude);
+            vec![finders().reverse.locate(shared.latitude, shared.longitude, lang_code).await]
+        }
+        (None, None) => return send_error(bot, msg, "error.query.empty", lang_code).await
     };
-    tracing::info!("Got a message query: {}", text);
-
-    let location = try_determine_location(from.id, &usr_client).await;
-    let locations = resolve_locations(text, lang_code, location).await?;
-    senders::send_locations_as_messages(bot, msg.chat.id, locations, lang_code).await?;
+    senders::send_locations_as_messages(bot, msg.chat.id, locations, lang_code, &SESSIONS).await?;
     Ok(())
 }
 
-#[tracing::instrument]
-async fn resolve_locations(query: String, lang_code: &str, location: Option<(f64, f64)>) -> Result<Vec<Location>, Box<dyn std::error::Error + Send + Sync>> {
+/// A single suggestion is resolved right away instead of sending a keyboard with one button.
+async fn resolve_single_suggestion(locations: Vec<Location>, uid: UserId, lang_code: &str) -> Vec<Location> {
+    let suggestion = match locations.as_slice() {
+        [location] => location.as_suggestion(),
+        _ => None
+    };
+    let Some(suggestion) = suggestion else {
+        return locations
+    };
+    let session_token = SESSIONS.end(uid).await;
+    with_session_token(session_token, PROVIDERS.resolve(&suggestion, lang_code)).await
+        .into_iter()
+        .collect()
+}
+
+#[tracing::instrument(skip(finders))]
+async fn resolve_locations(query: String, lang_code: &str, location: Option<(f64, f64)>, finders: &FinderChains) -> Result<Vec<Location>, Box<dyn std::error::Error + Send + Sync>> {
     let query = query.as_str();
-    let locations = if let Some(coords) = COORDS_REGEXP.captures(query) {
-        let lat: f64 = coords["latitude"].parse()?;
-        let long: f64 = coords["longitude"].parse()?;
-        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&long) {
-            return Err("coordinates out of range".into());
-        }
-        vec![Location::new(lat, long)]
+    let locations = if let Some(link) = links::parse(query) {
+        tracing::info!("Got a link to a map: {link:?}");
+        resolve_link(link, lang_code, location, finders).await
+    } else if let Some((lat, long)) = coords::parse(query) {
+        vec![finders.reverse.locate(lat, long, lang_code).await]
+    } else if let Some(code) = codes::parse(query) {
+        tracing::info!("Got a location code: {code:?}");
+        resolve_code(code, lang_code, location, finders).await
+    } else if let (Some(category), Some(point)) = (categories::parse(query), location) {
+        tracing::info!("Got a category: {category:?}");
+        resolve_category(category, query, lang_code, point, finders).await
     } else {
-        FINDER.find(query, lang_code, location).await
+        finders.search.find(query, lang_code, location).await
     };
     Ok(locations)
 }
 
+/// A pinned point is resolved as is; a name shown on a map is searched around the center of the map.
+async fn resolve_link(link: links::MapLink, lang_code: &str, location: Option<(f64, f64)>, finders: &FinderChains) -> Vec<Location> {
+    match (link.point, &link.name) {
+        (Some(_), Some(name)) if !link.marker => {
+            let bias = link.search_bias().or(location);
+            finders.search.find(name, lang_code, bias).await
+        }
+        (Some((lat, long)), name) => {
+            let loc = finders.reverse.locate(lat, long, lang_code).await;
+            vec![match name {
+                Some(name) => loc.with_name(name),
+                None => loc
+            }]
+        }
+        (None, Some(name)) => finders.search.find(name, lang_code, location).await,
+        (None, None) => Vec::default()
+    }
+}
+
+/// Places of the category are searched around the user; the query is geocoded as usual if there are none.
+async fn resolve_category(category: Category, query: &str, lang_code: &str, location: (f64, f64), finders: &FinderChains) -> Vec<Location> {
+    let locations = finders.nearby.find(category, query, lang_code, location).await;
+    if locations.is_empty() {
+        finders.search.find(query, lang_code, Some(location)).await
+    } else {
+        locations
+    }
+}
+
+/// A short plus code is recovered relative to its locality or, if the locality is omitted, to the user's location.
+async fn resolve_code(code: codes::LocationCode, lang_code: &str, location: Option<(f64, f64)>, finders: &FinderChains) -> Vec<Location> {
+    let point = match code {
+        codes::LocationCode::Point(lat, long) => Some((lat, long)),
+        codes::LocationCode::ShortPlusCode { code, locality } => {
+            let reference = match locality {
+                Some(locality) => locate_locality(&locality, lang_code, location, finders).await,
+                None => location,
+            };
+            reference.and_then(|reference| codes::recover_short_plus_code(&code, reference))
+        }
+    };
+    match point {
+        Some((lat, long)) => vec![finders.reverse.locate(lat, long, lang_code).await],
+        None => Vec::default()
+    }
+}
+
+/// Suggestions of autocomplete APIs have no coordinates, so any other place is preferred to them,
+/// and the first suggestion is resolved only if there is nothing else.
+async fn locate_locality(locality: &str, lang_code: &str, location: Option<(f64, f64)>, finders: &FinderChains) -> Option<(f64, f64)> {
+    let locations = finders.search.find(locality, lang_code, location).await;
+    if let Some(place) = locations.iter().find(|loc| loc.as_suggestion().is_none()) {
+        return Some((place.latitude(), place.longitude()))
+    }
+    let suggestion = locations.first()?.as_suggestion()?;
+    PROVIDERS.resolve(&suggestion, lang_code).await
+        .map(|place| (place.latitude(), place.longitude()))
+}
+
 async fn determine_lang_code(msg: &Message, usr_client: &UserService<impl UserServiceClient>) -> anyhow::Result<String> {
     let from = msg.from.as_ref().ok_or(anyhow!("no from"))?;
     Ok(ensure_lang_code(from.id, from.language_code.clone(), usr_client).await)
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use rust_i18n::t;
+use strum::IntoEnumIterator;
+use crate::loc::nearby::Category;
+
+const NEARBY_WORDS_KEY: &str = "category.nearby";
+
+/// Phrases of all languages, so that a Russian-speaking user may search for a "pharmacy" as well.
+static VOCABULARY: Lazy<Vocabulary> = Lazy::new(Vocabulary::load);
+
+struct Vocabulary {
+    phrases: HashMap<String, Category>,
+    nearby_words: Vec<String>,
+}
+
+impl Vocabulary {
+    fn load() -> Vocabulary {
+        let locales = rust_i18n::available_locales!();
+        let mut phrases = HashMap::new();
+        for category in Category::iter() {
+            let key = format!("category.{}", category.as_ref());
+            for locale in &locales {
+                phrases.extend(split_phrases(&t!(key.as_str(), locale = locale)).map(|phrase| (phrase, category)));
+            }
+        }
+        let mut nearby_words: Vec<String> = locales.iter()
+            .flat_map(|locale| split_phrases(&t!(NEARBY_WORDS_KEY, locale = locale)).collect::<Vec<_>>())
+            .collect();
+        // the longest words are stripped first, so that "near me" isn't left as "me"
+        nearby_words.sort_by_key(|word| std::cmp::Reverse(word.len()));
+        Vocabulary { phrases, nearby_words }
+    }
+}
+
+pub fn preload_vocabulary() {
+    let _ = *VOCABULARY;
+}
+
+/// Recognize `pharmacy`, `coffee near me` or `аптека рядом` by the vocabulary of the locales.
+pub fn parse(query: &str) -> Option<Category> {
+    let mut query = normalize(query);
+    for word in &VOCABULARY.nearby_words {
+        let rest = query.strip_prefix(&format!("{word} "))
+            .or_else(|| query.strip_suffix(&format!(" {word}")));
+        if let Some(rest) = rest {
+            query = rest.to_owned();
+        }
+    }
+    VOCABULARY.phrases.get(&query).copied()
+}
+
+fn split_phrases(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(',')
+        .map(normalize)
+        .filter(|phrase| !phrase.is_empty())
+}
+
+fn normalize(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || (c.is_ascii_punctuation() && c != '-'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -0,0 +1,22 @@
+use crate::loc::nearby::Category;
+use super::categories::parse;
+
+#[test]
+fn test_categories() {
+    assert_eq!(parse("pharmacy"), Some(Category::Pharmacy));
+    assert_eq!(parse("  Coffee  "), Some(Category::Cafe));
+    assert_eq!(parse("coffee shop near me"), Some(Category::Cafe));
+    assert_eq!(parse("nearest ATM"), Some(Category::Atm));
+    assert_eq!(parse("Аптека"), Some(Category::Pharmacy));
+    assert_eq!(parse("аптека рядом"), Some(Category::Pharmacy));
+    assert_eq!(parse("ближайшая заправка"), Some(Category::FuelStation));
+}
+
+#[test]
+fn test_not_categories() {
+    assert_eq!(parse(""), None);
+    assert_eq!(parse("near me"), None);
+    assert_eq!(parse("Pharmacy Lane 12"), None);
+    assert_eq!(parse("Eiffel Tower"), None);
+    assert_eq!(parse("аптека на Арбате"), None);
+}
@@ -0,0 +1,202 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use super::coords::check_range;
+
+const OLC_ALPHABET: &str = "23456789CFGHJMPQRVWX";
+const OLC_SEPARATOR: char = '+';
+const OLC_SEPARATOR_POSITION: usize = 8;
+const OLC_PADDING: char = '0';
+const OLC_PAIR_CODE_LENGTH: usize = 10;
+const OLC_GRID_ROWS: f64 = 5.0;
+const OLC_GRID_COLUMNS: f64 = 4.0;
+
+const GEOHASH_ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+/// Shorter geohashes are indistinguishable from postcodes like `H2X1Y4` or `1012AB`.
+const GEOHASH_MIN_LENGTH: usize = 7;
+const GEOHASH_MAX_LENGTH: usize = 12;
+
+/// British postcodes without the space, like `SW1W0NY` or `EC1A1BB`.
+static UK_POSTCODE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z]{1,2}\d[a-z\d]?\d[a-z]{2}$")
+    .expect("invalid regex for UK postcodes"));
+
+/// A code that is decoded locally, without any requests to the providers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocationCode {
+    /// Latitude and longitude of the center of the area encoded by a full plus code or a geohash.
+    Point(f64, f64),
+    /// A plus code without the first digits, which must be recovered from the locality or the user's location.
+    ShortPlusCode { code: String, locality: Option<String> },
+}
+
+/// Recognize `9G7VQJ8F+6X`, `9G8F+6X Moscow`, `Q8F+6X` or a geohash like `ucftpu4kc`.
+pub fn parse(query: &str) -> Option<LocationCode> {
+    let query = query.trim();
+    let (code, locality) = query.split_once(char::is_whitespace).unwrap_or((query, ""));
+    let locality = locality.trim().trim_start_matches(',').trim();
+
+    if code.contains(OLC_SEPARATOR) {
+        let code = code.to_uppercase();
+        let separator_position = code.find(OLC_SEPARATOR)?;
+        if separator_position == OLC_SEPARATOR_POSITION {
+            let (lat, lon) = decode_plus_code(&code)?;
+            Some(LocationCode::Point(lat, lon))
+        } else {
+            is_valid_short_plus_code(&code).then(|| LocationCode::ShortPlusCode {
+                code,
+                locality: Some(locality.to_owned()).filter(|l| !l.is_empty()),
+            })
+        }
+    } else if locality.is_empty() {
+        let (lat, lon) = decode_geohash(code)?;
+        Some(LocationCode::Point(lat, lon))
+    } else {
+        None
+    }
+}
+
+/// Decode a full plus code into the center of its area.
+fn decode_plus_code(code: &str) -> Option<(f64, f64)> {
+    let digits = plus_code_digits(code)?;
+    let first_lat = olc_value(*digits.first()?)?;
+    let first_lon = olc_value(*digits.get(1)?)?;
+    // the first latitude digit cannot exceed 180°/20°, the first longitude one — 360°/20°
+    if first_lat >= 9.0 || first_lon >= 18.0 {
+        return None
+    }
+
+    let (mut lat, mut lon) = (-90.0, -180.0);
+    let mut resolution = 20.0;
+    let (mut lat_resolution, mut lon_resolution) = (resolution, resolution);
+    for pair in digits.iter().take(OLC_PAIR_CODE_LENGTH).collect::<Vec<_>>().chunks(2) {
+        let [lat_digit, lon_digit] = pair[..] else {
+            return None
+        };
+        lat += olc_value(*lat_digit)? * resolution;
+        lon += olc_value(*lon_digit)? * resolution;
+        (lat_resolution, lon_resolution) = (resolution, resolution);
+        resolution /= 20.0;
+    }
+    for digit in digits.iter().skip(OLC_PAIR_CODE_LENGTH) {
+        let value = olc_value(*digit)?;
+        lat_resolution /= OLC_GRID_ROWS;
+        lon_resolution /= OLC_GRID_COLUMNS;
+        lat += (value / OLC_GRID_COLUMNS).floor() * lat_resolution;
+        lon += (value % OLC_GRID_COLUMNS) * lon_resolution;
+    }
+    check_range(lat + lat_resolution / 2.0, lon + lon_resolution / 2.0)
+}
+
+/// Digits of the code without the separator and the padding. The padding is allowed only in whole pairs before the separator.
+fn plus_code_digits(code: &str) -> Option<Vec<char>> {
+    let (before, after) = code.split_once(OLC_SEPARATOR)?;
+    let padding_start = before.find(OLC_PADDING);
+    if let Some(start) = padding_start {
+        let is_padding_valid = start >= 2 && start % 2 == 0
+            && before[start..].chars().all(|c| c == OLC_PADDING)
+            && after.is_empty();
+        if !is_padding_valid {
+            return None
+        }
+    }
+    if after.len() == 1 {
+        return None
+    }
+    let digits: Vec<char> = before[..padding_start.unwrap_or(before.len())].chars()
+        .chain(after.chars())
+        .collect();
+    digits.iter().all(|c| OLC_ALPHABET.contains(*c)).then_some(digits)
+}
+
+fn is_valid_short_plus_code(code: &str) -> bool {
+    let Some((before, after)) = code.split_once(OLC_SEPARATOR) else {
+        return false
+    };
+    before.len() >= 2 && before.len() < OLC_SEPARATOR_POSITION && before.len() % 2 == 0
+        && after.len() >= 2
+        && before.chars().chain(after.chars()).all(|c| OLC_ALPHABET.contains(c))
+}
+
+fn olc_value(digit: char) -> Option<f64> {
+    OLC_ALPHABET.find(digit).map(|i| i as f64)
+}
+
+/// Restore the full code closest to the reference point and decode it.
+/// See the `recoverNearest` function in the Open Location Code specification.
+pub fn recover_short_plus_code(code: &str, reference: (f64, f64)) -> Option<(f64, f64)> {
+    let missing_digits = OLC_SEPARATOR_POSITION - code.find(OLC_SEPARATOR)?;
+    let prefix: String = encode_plus_code_pairs(reference).chars()
+        .take(missing_digits)
+        .collect();
+    let (mut lat, mut lon) = decode_plus_code(&format!("{prefix}{code}"))?;
+
+    // the size of the area that the recovered digits point to
+    let resolution = 20_f64.powi(2 - (missing_digits / 2) as i32);
+    let half_resolution = resolution / 2.0;
+    let (ref_lat, ref_lon) = reference;
+    if ref_lat + half_resolution < lat && lat - resolution >= -90.0 {
+        lat -= resolution;
+    } else if ref_lat - half_resolution > lat && lat + resolution <= 90.0 {
+        lat += resolution;
+    }
+    if ref_lon + half_resolution < lon {
+        lon -= resolution;
+    } else if ref_lon - half_resolution > lon {
+        lon += resolution;
+    }
+    if lon > 180.0 {
+        lon -= 360.0;
+    } else if lon < -180.0 {
+        lon += 360.0;
+    }
+    check_range(lat, lon)
+}
+
+/// The first 8 digits of the plus code of the point.
+fn encode_plus_code_pairs((lat, lon): (f64, f64)) -> String {
+    let alphabet: Vec<char> = OLC_ALPHABET.chars().collect();
+    // the north pole belongs to the last cell
+    let mut lat = (lat + 90.0).clamp(0.0, 180.0 - 1e-10);
+    let mut lon = (lon + 180.0).rem_euclid(360.0);
+    let mut resolution = 20.0;
+    let mut code = String::with_capacity(OLC_SEPARATOR_POSITION);
+    while code.len() < OLC_SEPARATOR_POSITION {
+        let lat_digit = (lat / resolution).floor();
+        let lon_digit = (lon / resolution).floor();
+        lat -= lat_digit * resolution;
+        lon -= lon_digit * resolution;
+        code.push(alphabet[lat_digit as usize]);
+        code.push(alphabet[lon_digit as usize]);
+        resolution /= 20.0;
+    }
+    code
+}
+
+/// Decode a geohash into the center of its cell. Only strings with both digits and letters are considered geohashes
+/// to avoid treating ordinary words and numbers as them, and postcodes are excluded too.
+fn decode_geohash(hash: &str) -> Option<(f64, f64)> {
+    let hash = hash.to_lowercase();
+    let is_candidate = (GEOHASH_MIN_LENGTH..=GEOHASH_MAX_LENGTH).contains(&hash.len())
+        && hash.chars().any(|c| c.is_ascii_digit())
+        && hash.chars().any(|c| c.is_ascii_alphabetic())
+        && !UK_POSTCODE_REGEX.is_match(&hash);
+    if !is_candidate {
+        return None
+    }
+
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut is_lon_bit = true;
+    for c in hash.chars() {
+        let value = GEOHASH_ALPHABET.find(c)?;
+        for shift in (0..5).rev() {
+            let range: &mut (f64, f64) = if is_lon_bit { &mut lon_range } else { &mut lat_range };
+            let middle = (range.0 + range.1) / 2.0;
+            if (value >> shift) & 1 == 1 {
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            is_lon_bit = !is_lon_bit;
+        }
+    }
+    check_range((lat_range.0 + lat_range.1) / 2.0, (lon_range.0 + lon_range.1) / 2.0)
+}
@@ -0,0 +1,69 @@
+use super::codes::{parse, recover_short_plus_code, LocationCode};
+
+fn assert_point(code: Option<LocationCode>, expected: (f64, f64)) {
+    let Some(LocationCode::Point(lat, lon)) = code else {
+        panic!("{code:?} must be a point");
+    };
+    assert!((lat - expected.0).abs() < 1e-4 && (lon - expected.1).abs() < 1e-4,
+        "({lat}, {lon}) is not {expected:?}");
+}
+
+#[test]
+fn test_full_plus_codes() {
+    assert_point(parse("8FVC9G8F+6W"), (47.3655625, 8.5248125));
+    assert_point(parse("8fvc9g8f+6w"), (47.3655625, 8.5248125));
+    assert_point(parse("9G7VQJ8F+6X Moscow"), (55.7655625, 37.6249375));
+    assert_point(parse("8FVC9G8F+6WG"), (47.3655625, 8.5248281));
+    assert_point(parse("8FVC0000+"), (47.5, 8.5));
+}
+
+#[test]
+fn test_short_plus_codes() {
+    assert_eq!(parse("9G8F+6X Moscow"), Some(LocationCode::ShortPlusCode {
+        code: "9G8F+6X".to_owned(),
+        locality: Some("Moscow".to_owned()),
+    }));
+    assert_eq!(parse("CJ+2VX"), Some(LocationCode::ShortPlusCode { code: "CJ+2VX".to_owned(), locality: None }));
+
+    let (lat, lon) = recover_short_plus_code("9G8F+6W", (47.37, 8.52)).expect("the code must be recovered");
+    assert!((lat - 47.3655625).abs() < 1e-6 && (lon - 8.5248125).abs() < 1e-6, "({lat}, {lon})");
+
+    // the reference point is across the border of the cell: 9C3W9QCJ+2VX
+    let (lat, lon) = recover_short_plus_code("CJ+2VX", (51.3708675, -1.217765625)).expect("the code must be recovered");
+    assert!((lat - 51.3701125).abs() < 1e-4 && (lon - -1.217765625).abs() < 1e-4, "({lat}, {lon})");
+}
+
+#[test]
+fn test_geohashes() {
+    assert_point(parse("u4pruydqqvj"), (57.649111, 10.407440));
+    assert_point(parse("UCFV0NE"), (55.7590, 37.6220));
+}
+
+#[test]
+fn test_invalid_codes() {
+    let cases = [
+        "",
+        "Moscow",
+        "moscow",
+        "123456",
+        "ucfvbn",
+        "ucfv0n",
+        "H2X1Y4",
+        "1012ab",
+        "SW1A1AA",
+        "SW1W0NY",
+        "EC1A1BB",
+        "M11AE",
+        "u4pruydqqvjabc",
+        "u4pruy Moscow",
+        "8FVC9G8F+6",
+        "8FVC9G8F+6A",
+        "XFVC9G8F+6W",
+        "8F00C9G8+",
+        "8FVC0000+6W",
+        "G+6X",
+    ];
+    for case in cases {
+        assert_eq!(parse(case), None, "param: '{case}'");
+    }
+}
@@ -0,0 +1,243 @@
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Token {
+    Number(f64, bool),  // the flag tells whether the number has a fractional part
+    Degrees,
+    Minutes,
+    Seconds,
+    Hemisphere(Hemisphere),
+    Sign(f64),
+    Separator,
+    Space,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Hemisphere {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Hemisphere {
+    fn is_latitude(self) -> bool {
+        matches!(self, Hemisphere::North | Hemisphere::South)
+    }
+
+    fn sign(self) -> f64 {
+        match self {
+            Hemisphere::North | Hemisphere::East => 1.0,
+            Hemisphere::South | Hemisphere::West => -1.0,
+        }
+    }
+}
+
+/// Returns the latitude and the longitude if the whole query is a pair of valid coordinates written in one of the notations:
+/// - signed decimal degrees: `55.7558 37.6173`, `55,7558 37,6173`, `55.7558, 37.6173`;
+/// - degrees, minutes and seconds: `55°45′21″N 37°37′04″E`, `55°45'21" 37°37'04"`;
+/// - degrees and decimal minutes: `N 55 45.350 E 37 37.067`;
+/// - hemisphere letters as either suffixes or prefixes: `55.7558N, 37.6173E`, `S 33.86 E 151.21`.
+///
+/// The order of components marked with hemisphere letters doesn't matter. Two bare numbers are a latitude and a longitude
+/// unless the first one cannot be a latitude. Two numbers separated by a comma without whitespace are rejected,
+/// since the comma might be a decimal separator as well.
+pub fn parse(query: &str) -> Option<(f64, f64)> {
+    let tokens = tokenize(query.trim())?;
+    let has_hemispheres = tokens.iter().any(|t| matches!(t, Token::Hemisphere(_)));
+    let has_markers = tokens.iter().any(|t| matches!(t, Token::Degrees | Token::Minutes | Token::Seconds));
+
+    if has_hemispheres {
+        parse_with_hemispheres(tokens)
+    } else if has_markers {
+        parse_with_markers(tokens).and_then(|(first, second)| to_lat_lon(first, second))
+    } else {
+        parse_plain(&tokens).and_then(|(first, second)| to_lat_lon(first, second))
+    }
+}
+
+fn tokenize(query: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let token = match c {
+            '0'..='9' => {
+                let start = i;
+                while chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+                    i += 1;
+                }
+                let has_fraction = matches!(chars.get(i + 1), Some('.' | ','))
+                    && chars.get(i + 2).is_some_and(char::is_ascii_digit);
+                if has_fraction {
+                    i += 2;
+                    while chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+                        i += 1;
+                    }
+                }
+                let number: String = chars[start..=i].iter()
+                    .map(|&c| if c == ',' { '.' } else { c })
+                    .collect();
+                Token::Number(number.parse().ok()?, has_fraction)
+            }
+            '°' | 'º' | '˚' => Token::Degrees,
+            '\'' | '′' | '’' | '‘' | 'ʹ' | '´' if chars.get(i + 1) == Some(&c) => {
+                i += 1;
+                Token::Seconds
+            }
+            '\'' | '′' | '’' | '‘' | 'ʹ' | '´' => Token::Minutes,
+            '"' | '″' | '”' | '“' | 'ʺ' => Token::Seconds,
+            'N' | 'n' => Token::Hemisphere(Hemisphere::North),
+            'S' | 's' => Token::Hemisphere(Hemisphere::South),
+            'E' | 'e' => Token::Hemisphere(Hemisphere::East),
+            'W' | 'w' => Token::Hemisphere(Hemisphere::West),
+            '-' | '−' => Token::Sign(-1.0),
+            '+' => Token::Sign(1.0),
+            ',' | ';' => Token::Separator,
+            c if c.is_whitespace() => {
+                while chars.get(i + 1).is_some_and(|c| c.is_whitespace()) {
+                    i += 1;
+                }
+                Token::Space
+            }
+            _ => return None
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    Some(tokens)
+}
+
+/// `55°45′21″N 37°37′04″E`, `N 55 45.350 E 37 37.067`, `37.6173E, 55.7558N`
+fn parse_with_hemispheres(tokens: Vec<Token>) -> Option<(f64, f64)> {
+    let tokens: Vec<Token> = tokens.into_iter()
+        .filter(|t| *t != Token::Space)
+        .collect();
+    let is_prefix_style = matches!(tokens.first(), Some(Token::Hemisphere(_)));
+
+    let mut groups: Vec<(Hemisphere, Vec<Token>)> = Vec::with_capacity(2);
+    let mut current = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Hemisphere(hemisphere) if is_prefix_style => groups.push((hemisphere, Vec::new())),
+            Token::Hemisphere(hemisphere) => groups.push((hemisphere, std::mem::take(&mut current))),
+            token if is_prefix_style => groups.last_mut()?.1.push(token),
+            token => current.push(token),
+        }
+    }
+    if !current.iter().all(|t| *t == Token::Separator) {
+        return None
+    }
+
+    let [(first_hemisphere, first), (second_hemisphere, second)] = <[_; 2]>::try_from(groups).ok()?;
+    if first_hemisphere.is_latitude() == second_hemisphere.is_latitude() {
+        return None
+    }
+    let first = first_hemisphere.sign() * parse_component(trim_separators(&first))?;
+    let second = second_hemisphere.sign() * parse_component(trim_separators(&second))?;
+    let (latitude, longitude) = if first_hemisphere.is_latitude() {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    check_range(latitude, longitude)
+}
+
+/// `55°45′21″ 37°37′04″`, `-33° 52.1′, 151° 12.5′`: the second component starts with a number marked with the degree sign.
+fn parse_with_markers(tokens: Vec<Token>) -> Option<(f64, f64)> {
+    let tokens: Vec<Token> = tokens.into_iter()
+        .filter(|t| *t != Token::Space)
+        .collect();
+    let degrees: Vec<usize> = tokens.iter()
+        .enumerate()
+        .filter(|(_, t)| **t == Token::Degrees)
+        .map(|(i, _)| i)
+        .collect();
+    let [_, second_degrees] = degrees[..] else {
+        return None
+    };
+    let mut boundary = second_degrees.checked_sub(1)?;
+    if boundary > 0 && matches!(tokens[boundary - 1], Token::Sign(_)) {
+        boundary -= 1;
+    }
+    let first = parse_signed_component(trim_separators(&tokens[..boundary]))?;
+    let second = parse_signed_component(trim_separators(&tokens[boundary..]))?;
+    Some((first, second))
+}
+
+/// `55.7558 37.6173`, `55,7558 37,6173`, `-33.86, 151.21`
+fn parse_plain(tokens: &[Token]) -> Option<(f64, f64)> {
+    let (first, rest) = split_signed_number(tokens)?;
+    let rest = match rest {
+        [Token::Separator, Token::Space, rest @ ..] | [Token::Space, rest @ ..] => rest,
+        _ => return None
+    };
+    let (second, rest) = split_signed_number(rest)?;
+    rest.is_empty().then_some((first, second))
+}
+
+fn split_signed_number(tokens: &[Token]) -> Option<(f64, &[Token])> {
+    match tokens {
+        [Token::Sign(sign), Token::Number(number, _), rest @ ..] => Some((sign * number, rest)),
+        [Token::Number(number, _), rest @ ..] => Some((*number, rest)),
+        _ => None
+    }
+}
+
+fn parse_signed_component(tokens: &[Token]) -> Option<f64> {
+    match tokens {
+        [Token::Sign(sign), rest @ ..] => Some(sign * parse_component(rest)?),
+        _ => parse_component(tokens)
+    }
+}
+
+/// Degrees optionally followed by minutes and seconds. Only the last number may have a fractional part.
+fn parse_component(tokens: &[Token]) -> Option<f64> {
+    const MARKERS: [Token; 3] = [Token::Degrees, Token::Minutes, Token::Seconds];
+
+    let mut numbers: Vec<(f64, bool)> = Vec::with_capacity(3);
+    let mut rest = tokens;
+    while let [Token::Number(number, has_fraction), tail @ ..] = rest {
+        let position = numbers.len();
+        if position >= MARKERS.len() {
+            return None
+        }
+        rest = match tail {
+            [marker, tail @ ..] if *marker == MARKERS[position] => tail,
+            _ => tail,
+        };
+        numbers.push((*number, *has_fraction));
+    }
+    if !rest.is_empty() || numbers.is_empty() {
+        return None
+    }
+
+    let (_, whole) = numbers.split_last()?;
+    if whole.iter().any(|(_, has_fraction)| *has_fraction) {
+        return None
+    }
+    if numbers.iter().skip(1).any(|(value, _)| *value >= 60.0) {
+        return None
+    }
+    let value = numbers.iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|((value, _), divisor)| value / divisor)
+        .sum();
+    Some(value)
+}
+
+fn trim_separators(tokens: &[Token]) -> &[Token] {
+    let start = tokens.iter().position(|t| *t != Token::Separator).unwrap_or(tokens.len());
+    let end = tokens.iter().rposition(|t| *t != Token::Separator).map_or(start, |i| i + 1);
+    &tokens[start..end]
+}
+
+/// Components without hemisphere letters are expected in the `latitude, longitude` order,
+/// but a pair is swapped if only the swapped one is valid.
+fn to_lat_lon(first: f64, second: f64) -> Option<(f64, f64)> {
+    check_range(first, second).or_else(|| check_range(second, first))
+}
+
+pub(super) fn check_range(latitude: f64, longitude: f64) -> Option<(f64, f64)> {
+    let valid = (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude);
+    valid.then_some((latitude, longitude))
+}
@@ -0,0 +1,91 @@
+use super::coords::parse;
+
+fn assert_coords(query: &str, expected: (f64, f64)) {
+    let (lat, lon) = parse(query).unwrap_or_else(|| panic!("'{query}' must be parsed"));
+    assert!((lat - expected.0).abs() < 1e-4 && (lon - expected.1).abs() < 1e-4,
+        "'{query}' was parsed as ({lat}, {lon}) instead of {expected:?}");
+}
+
+#[test]
+fn test_decimal_degrees() {
+    let false_cases = [
+        "",
+        "  ",
+        ".,",
+        "!",
+        "123",
+        "1,2",
+        "1.2,3.4",
+        "1,2,3,4",
+    ];
+    let true_cases = [
+        "1 2",
+        "1.2 3.4",
+        "1,2 3,4",
+        "1.2  3.4",
+        "1,2  3,4",
+        "1.2, 3.4",
+        "12.345 67.89",
+    ];
+
+    for case in false_cases {
+        assert_eq!(parse(case), None, "param: '{case}'");
+    }
+    for case in true_cases {
+        assert!(parse(case).is_some(), "param: '{case}'");
+    }
+
+    assert_coords("55,7558 37,6173", (55.7558, 37.6173));
+    assert_coords("-33.8688, 151.2093", (-33.8688, 151.2093));
+    assert_coords("−33.8688 +151.2093", (-33.8688, 151.2093));
+}
+
+#[test]
+fn test_degrees_minutes_seconds() {
+    assert_coords("55°45′21″N 37°37′04″E", (55.7558, 37.6178));
+    assert_coords("55°45'21\"N, 37°37'04\"E", (55.7558, 37.6178));
+    assert_coords("55°45′21.5″ 37°37′04″", (55.7560, 37.6178));
+    assert_coords("33°52′08″S 151°12′31″E", (-33.8689, 151.2086));
+    assert_coords("-33° 52' 08'', 151° 12' 31''", (-33.8689, 151.2086));
+}
+
+#[test]
+fn test_decimal_minutes() {
+    assert_coords("N 55 45.350 E 37 37.067", (55.7558, 37.6178));
+    assert_coords("S33 52.14 E151 12.52", (-33.8690, 151.2087));
+    assert_coords("55°45.350′N 37°37.067′E", (55.7558, 37.6178));
+}
+
+#[test]
+fn test_hemisphere_letters() {
+    assert_coords("55.7558N, 37.6173E", (55.7558, 37.6173));
+    assert_coords("55.7558n 37.6173w", (55.7558, -37.6173));
+    assert_coords("37.6173E 55.7558N", (55.7558, 37.6173));
+    assert_coords("E 37.6173 N 55.7558", (55.7558, 37.6173));
+}
+
+#[test]
+fn test_swapped_order() {
+    assert_coords("151.2093 -33.8688", (-33.8688, 151.2093));
+}
+
+#[test]
+fn test_invalid_coords() {
+    let cases = [
+        "95 200",
+        "91N 37E",
+        "55N 37N",
+        "55E 37W",
+        "55°61′N 37°37′E",
+        "55°45′61″N 37°37′04″E",
+        "55.5°30′N 37°E",
+        "-55.7S 37.6E",
+        "55.7N",
+        "Ave 12",
+        "news",
+        "1 2 3",
+    ];
+    for case in cases {
+        assert_eq!(parse(case), None, "param: '{case}'");
+    }
+}
@@ -0,0 +1,258 @@
+use std::borrow::Cow;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use url::Url;
+use super::coords::check_range;
+
+/// Maps zoomed out more than this show a region rather than a place, so their center is not used as a search bias.
+const MIN_ZOOM_FOR_BIAS: f64 = 10.0;
+
+static GOOGLE_DATA_COORDS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"!3d(?P<latitude>-?\d+(\.\d+)?)!4d(?P<longitude>-?\d+(\.\d+)?)")
+    .expect("Invalid Google data coords regex!"));
+static GOOGLE_VIEWPORT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^@(?P<latitude>-?\d+(\.\d+)?),(?P<longitude>-?\d+(\.\d+)?)(,(?P<zoom>\d+(\.\d+)?)z)?")
+    .expect("Invalid Google viewport regex!"));
+static GEO_URI_LABEL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?P<coords>[^()]+)\((?P<label>.*)\)$")
+    .expect("Invalid geo URI label regex!"));
+
+/// A place or a map view extracted from a link to one of the popular map services or from a `geo:` URI.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MapLink {
+    /// Latitude and longitude.
+    pub point: Option<(f64, f64)>,
+    /// Whether the point is a pin dropped on the map rather than just the center of the view.
+    pub marker: bool,
+    pub name: Option<String>,
+    pub zoom: Option<f64>,
+}
+
+impl MapLink {
+    pub fn search_bias(&self) -> Option<(f64, f64)> {
+        self.point.filter(|_| self.zoom.is_none_or(|zoom| zoom >= MIN_ZOOM_FOR_BIAS))
+    }
+
+    fn set_marker(&mut self, point: Option<(f64, f64)>) {
+        if point.is_some() {
+            self.point = point;
+            self.marker = true;
+        }
+    }
+
+    fn set_center(&mut self, point: Option<(f64, f64)>) {
+        if !self.marker && point.is_some() {
+            self.point = point;
+        }
+    }
+
+    /// A query may contain either a name or coordinates of the place.
+    fn set_query(&mut self, query: &str) {
+        match parse_lat_lon(query) {
+            point @ Some(_) => self.set_marker(point),
+            None if !query.trim().is_empty() => self.name = Some(query.trim().to_owned()),
+            None => {}
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.point.is_none() && self.name.is_none()
+    }
+}
+
+/// Find the first link to a map in the text.
+pub fn parse(text: &str) -> Option<MapLink> {
+    text.split_whitespace()
+        .find_map(parse_link)
+}
+
+fn parse_link(token: &str) -> Option<MapLink> {
+    if token.get(..4).is_some_and(|scheme| scheme.eq_ignore_ascii_case("geo:")) {
+        return parse_geo_uri(&token[4..])
+    }
+
+    let url = if token.contains("://") {
+        Url::parse(token)
+    } else {
+        Url::parse(&format!("https://{token}"))
+    }.ok()?;
+    let host = url.host_str()?.trim_start_matches("www.");
+    let path = url.path();
+
+    let link = if host.starts_with("google.") && path.starts_with("/maps") || host.starts_with("maps.google.") {
+        parse_google(&url)
+    } else if host.starts_with("yandex.") && path.starts_with("/maps") || host.starts_with("maps.yandex.") {
+        parse_yandex(&url)
+    } else if host == "openstreetmap.org" || host == "osm.org" {
+        parse_osm(&url)
+    } else if host == "maps.apple.com" || host == "maps.apple" {
+        parse_apple(&url)
+    } else if host.starts_with("2gis.") {
+        parse_2gis(&url)
+    } else {
+        return None
+    };
+    Some(link).filter(|link| !link.is_empty())
+}
+
+/// RFC 5870: `geo:55.75,37.61[,alt][;u=35][?z=17]`. Android's `geo:0,0?q=55.75,37.61(Label)` and `geo:0,0?q=address` are supported as well.
+fn parse_geo_uri(uri: &str) -> Option<MapLink> {
+    let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+    let coords = path.split(';').next().unwrap_or_default();
+    let mut parts = coords.split(',');
+    let point = match (parts.next(), parts.next()) {
+        (Some(lat), Some(lon)) => check_range(lat.trim().parse().ok()?, lon.trim().parse().ok()?),
+        _ => None,
+    };
+
+    let mut link = MapLink::default();
+    link.set_marker(point.filter(|p| *p != (0.0, 0.0)));
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "z" => link.zoom = value.parse().ok(),
+            "q" => match GEO_URI_LABEL_REGEX.captures(&value) {
+                Some(caps) => {
+                    link.set_marker(parse_lat_lon(&caps["coords"]));
+                    link.name = Some(caps["label"].trim().to_owned()).filter(|label| !label.is_empty());
+                }
+                None => link.set_query(&value),
+            },
+            _ => {}
+        }
+    }
+    Some(link).filter(|link| !link.is_empty())
+}
+
+/// `/maps/place/Name/@55.75,37.61,17z/data=!3d55.75!4d37.61`, `/maps/search/?api=1&query=...`, `maps.google.com/?q=...`
+fn parse_google(url: &Url) -> MapLink {
+    let mut link = MapLink::default();
+    let segments: Vec<Cow<str>> = path_segments(url);
+    for (i, segment) in segments.iter().enumerate() {
+        if let Some(caps) = GOOGLE_VIEWPORT_REGEX.captures(segment) {
+            link.set_center(lat_lon_from_captures(&caps));
+            link.zoom = caps.name("zoom").and_then(|z| z.as_str().parse().ok());
+        } else if let Some(caps) = GOOGLE_DATA_COORDS_REGEX.captures(segment) {
+            link.set_marker(lat_lon_from_captures(&caps));
+        } else if i > 0 && matches!(segments[i - 1].as_ref(), "place" | "search") {
+            link.set_query(segment);
+        }
+    }
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "q" | "query" => link.set_query(&value),
+            "ll" | "center" => link.set_center(parse_lat_lon(&value)),
+            "z" | "zoom" => link.zoom = value.parse().ok(),
+            _ => {}
+        }
+    }
+    link
+}
+
+/// `/maps/?ll=37.61,55.75&z=17&pt=37.61,55.75&text=Name` and `?whatshere[point]=37.61,55.75`. Note the longitude goes first.
+fn parse_yandex(url: &Url) -> MapLink {
+    let mut link = MapLink::default();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            // several points are separated by '~'; each of them may have a style suffix: `37.61,55.75,pm2rdm`
+            "pt" | "whatshere[point]" => link.set_marker(value.split('~').next().and_then(parse_lon_lat)),
+            "ll" => link.set_center(parse_lon_lat(&value)),
+            "z" | "whatshere[zoom]" => link.zoom = value.parse().ok(),
+            "text" => link.set_query(&value),
+            _ => {}
+        }
+    }
+    link
+}
+
+/// `/?mlat=55.75&mlon=37.61#map=17/55.75/37.61` and `/search?query=...`
+fn parse_osm(url: &Url) -> MapLink {
+    let mut link = MapLink::default();
+    let (mut mlat, mut mlon) = (None, None);
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "mlat" => mlat = value.parse().ok(),
+            "mlon" => mlon = value.parse().ok(),
+            "query" => link.set_query(&value),
+            _ => {}
+        }
+    }
+    if let (Some(lat), Some(lon)) = (mlat, mlon) {
+        link.set_marker(check_range(lat, lon));
+    }
+
+    let view = url.fragment()
+        .and_then(|fragment| fragment.split('&').find_map(|param| param.strip_prefix("map=")));
+    if let Some(view) = view {
+        let parts: Vec<&str> = view.split('/').collect();
+        if let [zoom, lat, lon] = parts[..] {
+            link.zoom = zoom.parse().ok();
+            if let (Ok(lat), Ok(lon)) = (lat.parse(), lon.parse()) {
+                link.set_center(check_range(lat, lon));
+            }
+        }
+    }
+    link
+}
+
+/// `?ll=55.75,37.61&q=Name&z=17`, `?coordinate=55.75,37.61` and `?address=...`
+fn parse_apple(url: &Url) -> MapLink {
+    let mut link = MapLink::default();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "ll" | "coordinate" => link.set_marker(parse_lat_lon(&value)),
+            "sll" | "center" => link.set_center(parse_lat_lon(&value)),
+            "z" => link.zoom = value.parse().ok(),
+            "q" | "name" | "address" if link.name.is_none() => link.set_query(&value),
+            _ => {}
+        }
+    }
+    link
+}
+
+/// `/moscow/geo/4504127908538375/37.61,55.75`, `/moscow/search/Name` and `?m=37.61,55.75/17`. Note the longitude goes first.
+fn parse_2gis(url: &Url) -> MapLink {
+    let mut link = MapLink::default();
+    let segments: Vec<Cow<str>> = path_segments(url);
+    for (i, segment) in segments.iter().enumerate() {
+        if i > 0 && segments[i - 1] == "search" {
+            link.set_query(segment);
+        } else if let point @ Some(_) = parse_lon_lat(segment) {
+            link.set_marker(point);
+        }
+    }
+    for (key, value) in url.query_pairs() {
+        if key == "m" {
+            let (point, zoom) = value.split_once('/').unwrap_or((&value, ""));
+            link.set_center(parse_lon_lat(point));
+            link.zoom = zoom.parse().ok();
+        }
+    }
+    link
+}
+
+fn path_segments(url: &Url) -> Vec<Cow<'_, str>> {
+    url.path_segments()
+        .map(|segments| segments
+            .map(|segment| urlencoding::decode(segment).unwrap_or(Cow::Borrowed(segment)))
+            .map(|segment| match segment.contains('+') {
+                true => Cow::Owned(segment.replace('+', " ")),
+                false => segment,
+            })
+            .collect())
+        .unwrap_or_default()
+}
+
+fn lat_lon_from_captures(caps: &regex::Captures) -> Option<(f64, f64)> {
+    check_range(caps["latitude"].parse().ok()?, caps["longitude"].parse().ok()?)
+}
+
+fn parse_lat_lon(value: &str) -> Option<(f64, f64)> {
+    let mut parts = value.split(',').map(str::trim);
+    let lat = parts.next()?.parse().ok()?;
+    let lon = parts.next()?.parse().ok()?;
+    check_range(lat, lon)
+}
+
+fn parse_lon_lat(value: &str) -> Option<(f64, f64)> {
+    let mut parts = value.split(',').map(str::trim);
+    let lon = parts.next()?.parse().ok()?;
+    let lat = parts.next()?.parse().ok()?;
+    check_range(lat, lon)
+}
@@ -0,0 +1,96 @@
+use super::links::{parse, MapLink};
+
+fn marker(latitude: f64, longitude: f64) -> MapLink {
+    MapLink { point: Some((latitude, longitude)), marker: true, ..MapLink::default() }
+}
+
+fn center(latitude: f64, longitude: f64, zoom: f64) -> MapLink {
+    MapLink { point: Some((latitude, longitude)), zoom: Some(zoom), ..MapLink::default() }
+}
+
+fn named(name: &str) -> MapLink {
+    MapLink { name: Some(name.to_owned()), ..MapLink::default() }
+}
+
+#[test]
+fn test_google_links() {
+    assert_eq!(parse("https://maps.google.com/?q=55.7539,37.6208"), Some(marker(55.7539, 37.6208)));
+    assert_eq!(parse("https://www.google.com/maps/search/?api=1&query=Red+Square"), Some(named("Red Square")));
+    assert_eq!(parse("https://www.google.com/maps/@55.7539,37.6208,15z"), Some(center(55.7539, 37.6208, 15.0)));
+    assert_eq!(
+        parse("https://www.google.com/maps/place/Moscow+Kremlin/@55.752,37.617,17z/data=!3m1!4b1!4m6!3m5!1s0x0:0x0!8m2!3d55.7520263!4d37.6174994"),
+        Some(MapLink { name: Some("Moscow Kremlin".to_owned()), zoom: Some(17.0), ..marker(55.7520263, 37.6174994) })
+    );
+}
+
+#[test]
+fn test_yandex_links() {
+    assert_eq!(parse("yandex.ru/maps/?ll=37.6208,55.7539&z=16"), Some(center(55.7539, 37.6208, 16.0)));
+    assert_eq!(
+        parse("https://yandex.ru/maps/213/moscow/?ll=37.62,55.75&pt=37.6208,55.7539,pm2rdm~37.5,55.6&z=17"),
+        Some(MapLink { zoom: Some(17.0), ..marker(55.7539, 37.6208) })
+    );
+    assert_eq!(
+        parse("https://yandex.com/maps/?ll=37.62,55.75&text=coffee&z=14"),
+        Some(MapLink { name: Some("coffee".to_owned()), ..center(55.75, 37.62, 14.0) })
+    );
+}
+
+#[test]
+fn test_osm_links() {
+    assert_eq!(parse("https://www.openstreetmap.org/#map=17/55.75393/37.62079"), Some(center(55.75393, 37.62079, 17.0)));
+    assert_eq!(
+        parse("https://www.openstreetmap.org/?mlat=55.7539&mlon=37.6208#map=17/55.75/37.62"),
+        Some(MapLink { zoom: Some(17.0), ..marker(55.7539, 37.6208) })
+    );
+}
+
+#[test]
+fn test_apple_and_2gis_links() {
+    assert_eq!(
+        parse("https://maps.apple.com/?ll=55.7539,37.6208&q=Red%20Square"),
+        Some(MapLink { name: Some("Red Square".to_owned()), ..marker(55.7539, 37.6208) })
+    );
+    assert_eq!(parse("https://2gis.ru/moscow/geo/4504127908538375/37.6208,55.7539"), Some(marker(55.7539, 37.6208)));
+    assert_eq!(parse("https://2gis.ru/moscow?m=37.6208%2C55.7539%2F16"), Some(center(55.7539, 37.6208, 16.0)));
+    assert_eq!(parse("https://2gis.ru/moscow/search/Coffee%20house"), Some(named("Coffee house")));
+}
+
+#[test]
+fn test_geo_uris() {
+    assert_eq!(parse("geo:55.7539,37.6208"), Some(marker(55.7539, 37.6208)));
+    assert_eq!(parse("GEO:55.7539,37.6208,150;u=35"), Some(marker(55.7539, 37.6208)));
+    assert_eq!(parse("geo:55.7539,37.6208?z=18"), Some(MapLink { zoom: Some(18.0), ..marker(55.7539, 37.6208) }));
+    assert_eq!(
+        parse("geo:0,0?q=55.7539,37.6208(Red+Square)"),
+        Some(MapLink { name: Some("Red Square".to_owned()), ..marker(55.7539, 37.6208) })
+    );
+    assert_eq!(parse("geo:0,0?q=Red+Square"), Some(named("Red Square")));
+}
+
+#[test]
+fn test_non_links() {
+    let cases = [
+        "Red Square",
+        "55.7539 37.6208",
+        "https://example.com/maps/?q=55.7,37.6",
+        "https://www.google.com/search?q=Red+Square",
+        "geo:95,37.6",
+        "https://yandex.ru/maps/",
+    ];
+    for case in cases {
+        assert_eq!(parse(case), None, "param: '{case}'");
+    }
+}
+
+#[test]
+fn test_link_inside_text() {
+    assert_eq!(parse("Meet me here: geo:55.7539,37.6208 at 5pm"), Some(marker(55.7539, 37.6208)));
+}
+
+#[test]
+fn test_search_bias() {
+    assert_eq!(center(55.75, 37.62, 15.0).search_bias(), Some((55.75, 37.62)));
+    assert_eq!(center(55.75, 37.62, 5.0).search_bias(), None);
+    assert_eq!(named("Moscow").search_bias(), None);
+}
@@ -1,3 +1,17 @@
+pub mod links;
+pub mod coords;
+pub mod codes;
+pub mod categories;
+
+#[cfg(test)]
+mod links_test;
+#[cfg(test)]
+mod coords_test;
+#[cfg(test)]
+mod codes_test;
+#[cfg(test)]
+mod categories_test;
+
 use std::str::FromStr;
 use once_cell::sync::Lazy;
 
@@ -31,4 +45,5 @@ pub static QUERY_CHECK_MODE: Lazy<QueryCheckMode> = Lazy::new(
 
 pub fn preload_env_vars() {
     let _ = *QUERY_CHECK_MODE;
+    categories::preload_vocabulary();
 }
@@ -2,10 +2,17 @@ use once_cell::sync::Lazy;
 use rust_i18n::t;
 use teloxide::prelude::*;
 use teloxide::RequestError;
-use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryId, InlineQueryResult, InlineQueryResultLocation};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryId, InlineQueryResult, InlineQueryResultArticle, InlineQueryResultLocation, InlineQueryResultVenue, InputMessageContent, InputMessageContentLocation, InputMessageContentText};
 use teloxide::types::ReplyMarkup::InlineKeyboard;
+use reqwest::Url;
 use super::HandlerResult;
-use crate::loc::Location;
+use super::sessions::InlineSessions;
+use crate::loc::formatter;
+use crate::loc::suggest::Suggestion;
+use crate::loc::{Location, Provider};
+
+const MAX_HORIZONTAL_ACCURACY: f64 = 1500.0;
+pub const SUGGESTION_CALLBACK_PREFIX: &str = "suggestion:";
 
 static CACHE_TIME: Lazy<Option<u32>> = Lazy::new(|| std::env::var("CACHE_TIME")
     .ok()
@@ -17,15 +24,44 @@ static MSG_LOC_LIMIT: Lazy<usize> = Lazy::new(|| std::env::var("MSG_LOC_LIMIT")
     .unwrap_or(10)
 );
 
-pub async fn send_locations_inline(bot: Bot, query_id: InlineQueryId, lang_code: &str, locations: Vec<Location>) -> HandlerResult {
+/// Suggestions are sent as articles with a button, since their coordinates are unknown yet. Telegram reports the message
+/// of a chosen inline result only if it has a keyboard, so the message is edited once the suggestion is resolved.
+pub async fn send_locations_inline(bot: Bot, query_id: InlineQueryId, lang_code: &str, locations: Vec<Location>, sessions: &InlineSessions) -> HandlerResult {
+    let mut suggestions = Vec::new();
     let results: Vec<InlineQueryResult> = locations.iter()
         .map(|l| {
             let uuid = uuid::Uuid::new_v4().to_string();
-            let address = l.address().unwrap_or(t!("title.address.point", locale = lang_code).to_string());
-            InlineQueryResult::Location(
-                InlineQueryResultLocation::new(uuid, address, l.latitude(), l.longitude())
-            )})
+            let formatted = formatter::format(l, lang_code);
+            let title = formatted.as_ref()
+                .map(|f| f.title.clone())
+                .unwrap_or(t!("title.address.point", locale = lang_code).to_string());
+            let distance = l.distance().map(|distance| format_distance(distance, lang_code));
+            let description = [distance, formatted.and_then(|f| f.description)]
+                .into_iter()
+                .flatten()
+                .reduce(|first, second| format!("{first} · {second}"));
+            if let Some(suggestion) = l.as_suggestion() {
+                let content = InputMessageContent::Text(InputMessageContentText::new(suggestion.text.clone()));
+                let mut result = InlineQueryResultArticle::new(uuid.clone(), title, content);
+                result.description = description;
+                result.reply_markup = map_keyboard(&suggestion, None, lang_code);
+                suggestions.push((uuid, suggestion));
+                return InlineQueryResult::Article(result)
+            }
+            match description {
+                // location results have no description, so a venue is used to show it under the title
+                Some(description) => InlineQueryResult::Venue(
+                    InlineQueryResultVenue::new(uuid, l.latitude(), l.longitude(), title, description)
+                        .input_message_content(InputMessageContent::Location(location_content(l)))
+                ),
+                None => {
+                    let mut result = InlineQueryResultLocation::new(uuid, title, l.latitude(), l.longitude());
+                    result.horizontal_accuracy = accuracy(l);
+                    InlineQueryResult::Location(result)
+                }
+            }})
         .collect();
+    sessions.save_suggestions(&suggestions).await;
 
     let mut answer = bot.answer_inline_query(query_id, results);
     answer.cache_time = *CACHE_TIME;
@@ -35,25 +71,50 @@ pub async fn send_locations_inline(bot: Bot, query_id: InlineQueryId, lang_code:
     }
 }
 
-pub async fn send_locations_as_messages(bot: Bot, chat_id: ChatId, locations: Vec<Location>, lang_code: &str) -> Result<Message, RequestError> {
+/// Suggestions are resolved by the buttons, so a single one must be resolved beforehand.
+pub async fn send_locations_as_messages(bot: Bot, chat_id: ChatId, locations: Vec<Location>, lang_code: &str, sessions: &InlineSessions) -> Result<Message, RequestError> {
     match locations.len() {
         0 => bot.send_message(chat_id, t!("title.address-list.empty", locale = lang_code)).await,
         1 => send_single_location(&bot, chat_id, locations.first().unwrap()).await,
-        _ => send_locations_keyboard(&bot, chat_id, locations, lang_code).await
+        _ => send_locations_keyboard(&bot, chat_id, locations, lang_code, sessions).await
     }
 }
 
-async fn send_locations_keyboard(bot: &Bot, chat_id: ChatId, locations: Vec<Location>, lang_code: &str) -> Result<Message, RequestError> {
+/// Replace the text of an inline message sent for a suggestion with the resolved address.
+pub async fn complete_inline_suggestion(bot: Bot, inline_message_id: String, suggestion: &Suggestion, location: &Location, lang_code: &str) -> HandlerResult {
+    let line = formatter::format(location, lang_code)
+        .map(|formatted| formatted.line)
+        .unwrap_or_else(|| suggestion.text.clone());
+    let mut req = bot.edit_message_text_inline(inline_message_id, format!("{line}\n{}, {}", location.latitude(), location.longitude()));
+    req.reply_markup = map_keyboard(suggestion, Some(location), lang_code);
+    req.await?;
+    Ok(())
+}
+
+async fn send_locations_keyboard(bot: &Bot, chat_id: ChatId, locations: Vec<Location>, lang_code: &str, sessions: &InlineSessions) -> Result<Message, RequestError> {
+    let mut suggestions = Vec::new();
     let buttons: Vec<Vec<InlineKeyboardButton>> = locations.iter()
-        .filter(|l| l.address().is_some())
+        .filter_map(|loc| formatter::format(loc, lang_code).map(|formatted| (loc, formatted)))
         .take(*MSG_LOC_LIMIT)
-        .map(|loc| {
-            let addr = loc.address().unwrap();
-            let data = format!("{},{}", loc.latitude(), loc.longitude());
+        .map(|(loc, formatted)| {
+            let addr = match loc.distance() {
+                Some(distance) => format!("{} · {}", format_distance(distance, lang_code), formatted.line),
+                None => formatted.line
+            };
+            let data = match loc.as_suggestion() {
+                Some(suggestion) => {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    let data = format!("{SUGGESTION_CALLBACK_PREFIX}{id}");
+                    suggestions.push((id, suggestion));
+                    data
+                }
+                None => format!("{},{}", loc.latitude(), loc.longitude()),
+            };
             let btn = InlineKeyboardButton::callback(addr.clone(), data);
             vec!(btn)
         })
         .collect();
+    sessions.save_suggestions(&suggestions).await;
 
     let mut msg = bot.send_message(chat_id, t!("title.address-list.has-data", locale = lang_code));
     let keyboard = InlineKeyboardMarkup::new(buttons);
@@ -68,4 +129,44 @@ async fn send_single_location(bot: &Bot, chat_id: ChatId, location: &Location) -
         bot.send_message(chat_id, addr).await?;
     }
     bot.send_location(chat_id, location.latitude(), location.longitude()).await
-}
\ No newline at end of file
+}
+
+/// A button opening the place on the map of its provider, by the coordinates if the suggestion is resolved already.
+fn map_keyboard(suggestion: &Suggestion, location: Option<&Location>, lang_code: &str) -> Option<InlineKeyboardMarkup> {
+    let query = match location {
+        Some(location) => format!("{},{}", location.latitude(), location.longitude()),
+        None => suggestion.text.clone(),
+    };
+    let url = match suggestion.provider {
+        Provider::Google => Url::parse_with_params("https://www.google.com/maps/search/",
+            &[("api", "1"), ("query", &query), ("query_place_id", &suggestion.place_id)]),
+        Provider::Yandex => Url::parse_with_params("https://yandex.ru/maps/", &[("text", &query)]),
+        _ => Url::parse_with_params("https://www.openstreetmap.org/search", &[("query", &query)]),
+    };
+    let url = url
+        .inspect_err(|err| log::error!("couldn't build a link to the map for {suggestion:?}: {err}"))
+        .ok()?;
+    let button = InlineKeyboardButton::url(t!("title.suggestion.map", locale = lang_code), url);
+    Some(InlineKeyboardMarkup::new(vec![vec![button]]))
+}
+
+fn location_content(location: &Location) -> InputMessageContentLocation {
+    let mut content = InputMessageContentLocation::new(location.latitude(), location.longitude());
+    content.horizontal_accuracy = accuracy(location);
+    content
+}
+
+/// Telegram accepts the accuracy radius in the range of 0-1500 meters; larger areas are sent as plain points.
+fn accuracy(location: &Location) -> Option<f64> {
+    location.radius().filter(|radius| *radius <= MAX_HORIZONTAL_ACCURACY)
+}
+
+fn format_distance(meters: f64, lang_code: &str) -> String {
+    if meters < 1000.0 {
+        let distance = ((meters / 10.0).round() * 10.0).to_string();
+        t!("title.distance.meters", locale = lang_code, distance = distance).to_string()
+    } else {
+        let distance = format!("{:.1}", meters / 1000.0);
+        t!("title.distance.kilometers", locale = lang_code, distance = distance).to_string()
+    }
+}
@@ -0,0 +1,104 @@
+use derive_more::Constructor;
+use mobc_redis::{redis, RedisConnectionManager};
+use mobc_redis::redis::AsyncCommands;
+use teloxide::types::UserId;
+use crate::loc::suggest::Suggestion;
+
+const SESSION_KEY_PREFIX: &str = "inline-session.";
+const SUGGESTION_KEY_PREFIX: &str = "inline-suggestion.";
+
+/// Google ends an autocomplete session a few minutes after its start anyway.
+const DEFAULT_SESSION_TTL_SECS: u64 = 180;
+/// Suggestions must outlive the cache of inline results and the keyboards of messages.
+const SUGGESTION_TTL_SECS: u64 = 86400;
+
+/// Autocomplete sessions of the users and the suggestions sent to them. A session lasts while the user keeps typing
+/// an inline query, and ends when one of the suggestions is chosen. Both are kept in Redis, so that the requests of
+/// the same user may be processed by different replicas.
+#[derive(Constructor)]
+pub struct InlineSessions {
+    pool: mobc::Pool<RedisConnectionManager>,
+    session_ttl: u64,
+}
+
+impl InlineSessions {
+    pub fn from_env(redis_pool: &mobc::Pool<RedisConnectionManager>) -> Self {
+        let session_ttl = std::env::var("INLINE_SESSION_TTL_SECS").ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+        Self::new(redis_pool.clone(), session_ttl)
+    }
+
+    /// The token of the current session of the user. A new session is started if there is none.
+    /// `None` if Redis is unavailable: the requests are billed one by one then.
+    pub async fn token(&self, uid: UserId) -> Option<String> {
+        self.fetch_token(uid).await
+            .inspect_err(|e| log::error!("couldn't fetch the session token of {uid}: {e}"))
+            .ok()
+    }
+
+    /// End the current session of the user and return its token.
+    pub async fn end(&self, uid: UserId) -> Option<String> {
+        let key = SESSION_KEY_PREFIX.to_string() + uid.to_string().as_str();
+        let result: anyhow::Result<Option<String>> = async {
+            let mut conn = self.pool.get().await?;
+            Ok(conn.get_del(key).await?)
+        }.await;
+        result
+            .inspect_err(|e| log::error!("couldn't end the session of {uid}: {e}"))
+            .ok()
+            .flatten()
+    }
+
+    /// Remember the suggestions by the identifiers of the results or buttons they were sent with.
+    pub async fn save_suggestions(&self, suggestions: &[(String, Suggestion)]) {
+        if suggestions.is_empty() {
+            return
+        }
+        if let Err(e) = self.store_suggestions(suggestions).await {
+            log::error!("couldn't save the suggestions: {e}");
+        }
+    }
+
+    pub async fn suggestion(&self, id: &str) -> Option<Suggestion> {
+        let result: anyhow::Result<Option<String>> = async {
+            let mut conn = self.pool.get().await?;
+            Ok(conn.get(SUGGESTION_KEY_PREFIX.to_string() + id).await?)
+        }.await;
+        result
+            .inspect_err(|e| log::error!("couldn't fetch the suggestion {id}: {e}"))
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json)
+                .inspect_err(|e| log::error!("couldn't deserialize the suggestion {id}: {e}"))
+                .ok())
+    }
+
+    async fn fetch_token(&self, uid: UserId) -> anyhow::Result<String> {
+        let key = SESSION_KEY_PREFIX.to_string() + uid.to_string().as_str();
+        let new_token = uuid::Uuid::new_v4().to_string();
+        let mut conn = self.pool
+            .get().await?
+            .into_inner();
+        // every query prolongs the session
+        let (token,): (String,) = redis::pipe().atomic()
+            .set_options(&key, new_token, redis::SetOptions::default()
+                .conditional_set(redis::ExistenceCheck::NX)).ignore()
+            .get(&key)
+            .expire(&key, self.session_ttl as i64).ignore()
+            .query_async(&mut conn).await?;
+        Ok(token)
+    }
+
+    async fn store_suggestions(&self, suggestions: &[(String, Suggestion)]) -> anyhow::Result<()> {
+        let mut conn = self.pool
+            .get().await?
+            .into_inner();
+        let mut pipe = redis::pipe();
+        for (id, suggestion) in suggestions {
+            pipe.set_ex(SUGGESTION_KEY_PREFIX.to_string() + id, serde_json::to_string(suggestion)?, SUGGESTION_TTL_SECS).ignore();
+        }
+        pipe.query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+}
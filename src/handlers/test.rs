@@ -1,4 +1,9 @@
-use super::{is_query_correct, COORDS_REGEXP, QUERY_REGEX};
+use async_trait::async_trait;
+use serde_json::json;
+use crate::loc::{self, LocFinder, LocResult, Location, SearchChain};
+use crate::loc::nearby::NearbySearchChain;
+use crate::loc::reverse::ReverseSearchChain;
+use super::{is_query_correct, FinderChains, QUERY_REGEX};
 
 mod otel {
     use opentelemetry_sdk::trace::InMemorySpanExporter;
@@ -18,31 +23,6 @@ mod otel {
     }
 }
 
-#[test]
-fn test_coords_regex() {
-    let false_cases = [
-        "",
-        "  ",
-        ".,",
-        "!",
-        "123",
-        "1,2",
-        "1.2,3.4",
-        "1,2,3,4",
-    ];
-    let true_cases = [
-        "1 2",
-        "1.2 3.4",
-        "1,2 3,4",
-        "1.2  3.4",
-        "1,2  3,4",
-        "1.2, 3.4",
-        "12.345 67.89",
-    ];
-
-    run_test(false_cases, true_cases, |case| COORDS_REGEXP.is_match(case))
-}
-
 #[test]
 fn test_query_regex() {
     let false_cases = [
@@ -109,7 +89,7 @@ async fn test_resolve_locations_creates_span() {
     let (exporter, _provider, subscriber) = otel::setup_otel_test();
     let _guard = tracing::subscriber::set_default(subscriber);
 
-    let result = super::resolve_locations("55.7 37.6".to_string(), "en", None).await;
+    let result = super::resolve_locations("55.7 37.6".to_string(), "en", None, &stub_finders()).await;
     assert!(result.is_ok());
 
     let spans = exporter.get_finished_spans().unwrap();
@@ -128,7 +108,7 @@ async fn test_span_hierarchy() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     let root_span = tracing::info_span!("root");
-    super::resolve_locations("55.7 37.6".to_string(), "en", None)
+    super::resolve_locations("55.7 37.6".to_string(), "en", None, &stub_finders())
         .instrument(root_span)
         .await
         .unwrap();
@@ -149,6 +129,39 @@ async fn test_span_hierarchy() {
     );
 }
 
+#[tokio::test]
+async fn test_short_plus_code_ignores_suggestions() {
+    let suggestion: Location = serde_json::from_value(json!({
+        "address": "Zürich, Switzerland",
+        "latitude": 0.0,
+        "longitude": 0.0,
+        "provider": "google",
+        "place_id": "ChIJGaK-SZcLkEcRA9wf5_GNbuY",
+        "kind": "Other",
+        "components": {},
+        "suggestion": true,
+    })).unwrap();
+    let finders = FinderChains {
+        search: SearchChain::new(vec![loc::finder("", StubLocFinder(vec![suggestion, Location::new(47.37, 8.52)]))]),
+        ..stub_finders()
+    };
+
+    // the code is recovered relative to the place, not to the zero coordinates of the suggestion
+    let result = super::resolve_locations("9G8F+6W Zurich".to_string(), "en", None, &finders).await.unwrap();
+    let [loc] = result.as_slice() else {
+        panic!("a single location is expected: {result:?}")
+    };
+    assert!((loc.latitude() - 47.3655625).abs() < 1e-6 && (loc.longitude() - 8.5248125).abs() < 1e-6, "{loc:?}");
+}
+
+fn stub_finders() -> FinderChains {
+    FinderChains {
+        search: SearchChain::new(vec![]),
+        reverse: ReverseSearchChain::new(vec![]),
+        nearby: NearbySearchChain::new(vec![]),
+    }
+}
+
 fn run_test<const N1: usize, const N2: usize>(
     false_cases: [&str; N1],
     true_cases: [&str; N2],
@@ -161,3 +174,12 @@ fn run_test<const N1: usize, const N2: usize>(
         assert_eq!(expected, runner(param), "param: '{param}'");
     }
 }
+
+struct StubLocFinder(Vec<Location>);
+
+#[async_trait]
+impl LocFinder for StubLocFinder {
+    async fn find(&self, _query: &str, _lang_code: &str, _location: Option<(f64, f64)>) -> LocResult {
+        Ok(self.0.clone())
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use once_cell::sync::Lazy;
+use prometheus::Opts;
+use crate::metrics;
+use super::cache::get_env_or_default;
+
+const ENV_FAILURE_THRESHOLD: &str = "CIRCUIT_BREAKER_FAILURE_THRESHOLD";
+const ENV_SLOW_CALL_MS: &str = "CIRCUIT_BREAKER_SLOW_CALL_MS";
+const ENV_COOLDOWN_SECS: &str = "CIRCUIT_BREAKER_COOLDOWN_SECS";
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_SLOW_CALL_MS: u64 = 5000;
+const DEFAULT_COOLDOWN_SECS: u64 = 30;
+
+static SETTINGS: Lazy<BreakerSettings> = Lazy::new(|| {
+    let settings = BreakerSettings {
+        failure_threshold: get_env_or_default(ENV_FAILURE_THRESHOLD, DEFAULT_FAILURE_THRESHOLD),
+        slow_call: Duration::from_millis(get_env_or_default(ENV_SLOW_CALL_MS, DEFAULT_SLOW_CALL_MS)),
+        cooldown: Duration::from_secs(get_env_or_default(ENV_COOLDOWN_SECS, DEFAULT_COOLDOWN_SECS)),
+    };
+    tracing::info!("circuit breaker settings: {settings:?}");
+    settings
+});
+
+static STATE_GAUGE: Lazy<prometheus::GaugeVec> = Lazy::new(|| {
+    let opts = Opts::new("finder_circuit_breaker_state", "state of the circuit breaker of a finder: 0 — closed, 1 — open, 2 — half-open");
+    metrics::REGISTRY.register_gauge_vec("circuit breaker state", opts, &["provider"])
+});
+static FAILURES_GAUGE: Lazy<prometheus::GaugeVec> = Lazy::new(|| {
+    let opts = Opts::new("finder_consecutive_failures", "count of consecutive failed or slow calls to a finder");
+    metrics::REGISTRY.register_gauge_vec("consecutive failures", opts, &["provider"])
+});
+static LATENCY_GAUGE: Lazy<prometheus::GaugeVec> = Lazy::new(|| {
+    let opts = Opts::new("finder_last_call_latency_seconds", "latency of the last call to a finder");
+    metrics::REGISTRY.register_gauge_vec("finder latency", opts, &["provider"])
+});
+
+#[derive(Debug, Copy, Clone)]
+pub struct BreakerSettings {
+    pub failure_threshold: u32,
+    /// Successful calls that took longer than this are counted as failures.
+    pub slow_call: Duration,
+    pub cooldown: Duration,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probe_in_flight: bool },
+}
+
+impl State {
+    fn as_gauge_value(&self) -> f64 {
+        match self {
+            State::Closed => 0.0,
+            State::Open { .. } => 1.0,
+            State::HalfOpen { .. } => 2.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: State,
+    consecutive_failures: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the circuit breaker of the '{0}' finder is open")]
+pub struct CircuitOpen(pub String);
+
+/// Skips a sick provider for a cooldown after several consecutive failures or slow calls,
+/// and lets a single probe call through afterwards to check if the provider has recovered.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    provider: String,
+    settings: BreakerSettings,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(provider: &str) -> CircuitBreaker {
+        Self::with_settings(provider, *SETTINGS)
+    }
+
+    pub fn with_settings(provider: &str, settings: BreakerSettings) -> CircuitBreaker {
+        let breaker = CircuitBreaker {
+            provider: provider.to_owned(),
+            settings,
+            inner: Mutex::new(Inner {
+                state: State::Closed,
+                consecutive_failures: 0,
+            }),
+        };
+        breaker.export(&breaker.lock());
+        breaker
+    }
+
+    /// Check if a call is permitted. In the half-open state only one probe call is allowed at a time.
+    pub fn try_acquire(&self) -> bool {
+        let mut inner = self.lock();
+        let allowed = match inner.state {
+            State::Closed => true,
+            State::Open { until } if Instant::now() >= until => {
+                tracing::info!("the circuit breaker of the '{}' finder is half-open now", self.provider);
+                inner.state = State::HalfOpen { probe_in_flight: true };
+                true
+            }
+            State::Open { .. } => false,
+            State::HalfOpen { probe_in_flight: false } => {
+                inner.state = State::HalfOpen { probe_in_flight: true };
+                true
+            }
+            State::HalfOpen { probe_in_flight: true } => false,
+        };
+        self.export(&inner);
+        allowed
+    }
+
+    pub fn record(&self, success: bool, elapsed: Duration) {
+        LATENCY_GAUGE.with_label_values(&[&self.provider]).set(elapsed.as_secs_f64());
+
+        let success = success && elapsed <= self.settings.slow_call;
+        let mut inner = self.lock();
+        if success {
+            if inner.state != State::Closed {
+                tracing::info!("the circuit breaker of the '{}' finder is closed again", self.provider);
+            }
+            inner.state = State::Closed;
+            inner.consecutive_failures = 0;
+        } else {
+            inner.consecutive_failures += 1;
+            let probe_failed = matches!(inner.state, State::HalfOpen { .. });
+            if probe_failed || inner.consecutive_failures >= self.settings.failure_threshold {
+                tracing::warn!("the circuit breaker of the '{}' finder is open for {:?} after {} failures",
+                    self.provider, self.settings.cooldown, inner.consecutive_failures);
+                inner.state = State::Open { until: Instant::now() + self.settings.cooldown };
+            }
+        }
+        self.export(&inner);
+    }
+
+    /// Open the circuit at once if the provider is known to be unusable for a while, e.g. its quota is exhausted.
+    pub fn trip(&self, elapsed: Duration) {
+        LATENCY_GAUGE.with_label_values(&[&self.provider]).set(elapsed.as_secs_f64());
+
+        let mut inner = self.lock();
+        inner.consecutive_failures += 1;
+        tracing::warn!("the circuit breaker of the '{}' finder is tripped for {:?}", self.provider, self.settings.cooldown);
+        inner.state = State::Open { until: Instant::now() + self.settings.cooldown };
+        self.export(&inner);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn export(&self, inner: &Inner) {
+        STATE_GAUGE.with_label_values(&[&self.provider]).set(inner.state.as_gauge_value());
+        FAILURES_GAUGE.with_label_values(&[&self.provider]).set(f64::from(inner.consecutive_failures));
+    }
+}
+
+/// Records a failure if the call was cancelled before completion (e.g. when the chain's deadline is hit).
+pub(super) struct CallGuard<'a> {
+    breaker: &'a CircuitBreaker,
+    started: Instant,
+    finished: bool,
+}
+
+impl<'a> CallGuard<'a> {
+    pub fn start(breaker: &'a CircuitBreaker) -> Self {
+        CallGuard { breaker, started: Instant::now(), finished: false }
+    }
+
+    pub fn finish(mut self, success: bool) {
+        self.finished = true;
+        self.breaker.record(success, self.started.elapsed());
+    }
+
+    pub fn trip(mut self) {
+        self.finished = true;
+        self.breaker.trip(self.started.elapsed());
+    }
+}
+
+impl Drop for CallGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.breaker.record(false, self.started.elapsed());
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+use super::breaker::{BreakerSettings, CallGuard, CircuitBreaker};
+
+const SETTINGS: BreakerSettings = BreakerSettings {
+    failure_threshold: 2,
+    slow_call: Duration::from_millis(500),
+    cooldown: Duration::from_millis(50),
+};
+
+#[test]
+fn test_breaker_opens_after_consecutive_failures() {
+    let breaker = CircuitBreaker::with_settings("test-open", SETTINGS);
+
+    assert!(breaker.try_acquire());
+    breaker.record(false, Duration::ZERO);
+    assert!(breaker.try_acquire());
+    breaker.record(true, Duration::ZERO);   // the counter is reset by a success
+    breaker.record(false, Duration::ZERO);
+    assert!(breaker.try_acquire());
+    breaker.record(false, Duration::ZERO);
+    assert!(!breaker.try_acquire());
+}
+
+#[test]
+fn test_breaker_counts_slow_calls_as_failures() {
+    let breaker = CircuitBreaker::with_settings("test-slow", SETTINGS);
+
+    breaker.record(true, Duration::from_secs(1));
+    breaker.record(true, Duration::from_secs(1));
+    assert!(!breaker.try_acquire());
+}
+
+#[test]
+fn test_breaker_half_open_probe() {
+    let breaker = CircuitBreaker::with_settings("test-half-open", SETTINGS);
+    breaker.record(false, Duration::ZERO);
+    breaker.record(false, Duration::ZERO);
+    assert!(!breaker.try_acquire());
+
+    std::thread::sleep(SETTINGS.cooldown);
+    assert!(breaker.try_acquire(), "a probe must be let through after the cooldown");
+    assert!(!breaker.try_acquire(), "only one probe is allowed at a time");
+
+    // a failed probe opens the circuit again
+    breaker.record(false, Duration::ZERO);
+    assert!(!breaker.try_acquire());
+
+    std::thread::sleep(SETTINGS.cooldown);
+    assert!(breaker.try_acquire());
+    breaker.record(true, Duration::ZERO);
+    assert!(breaker.try_acquire());
+    assert!(breaker.try_acquire());
+}
+
+#[test]
+fn test_cancelled_call_is_a_failure() {
+    let breaker = CircuitBreaker::with_settings("test-cancelled", SETTINGS);
+    drop(CallGuard::start(&breaker));
+    drop(CallGuard::start(&breaker));
+    assert!(!breaker.try_acquire());
+}
+
+#[test]
+fn test_tripped_breaker_opens_at_once() {
+    let breaker = CircuitBreaker::with_settings("test-trip", SETTINGS);
+    CallGuard::start(&breaker).trip();
+    assert!(!breaker.try_acquire());
+
+    std::thread::sleep(SETTINGS.cooldown);
+    assert!(breaker.try_acquire(), "a probe must be let through after the cooldown");
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use async_trait::async_trait;
+use http::Extensions;
+use http_cache::CacheMode;
+use mobc::Pool;
+use mobc_redis::{redis, RedisConnectionManager};
+use prometheus::Opts;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use super::cache::{from_cache, is_stale_lookup};
+use crate::metrics;
+
+const REDIS_KEY_PREFIX: &str = "loc-budget";
+const ENV_PREFIX: &str = "BUDGET_";
+
+const DAILY_KEY_TTL_SECS: i64 = 2 * 24 * 60 * 60;
+const MONTHLY_KEY_TTL_SECS: i64 = 32 * 24 * 60 * 60;
+
+#[derive(Debug, thiserror::Error)]
+#[error("the {period} budget for {provider}/{api} is exhausted")]
+pub struct BudgetExceeded {
+    provider: String,
+    api: String,
+    period: Period,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum_macros::Display, strum_macros::AsRefStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Period {
+    Daily,
+    Monthly,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Limits {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+impl Limits {
+    fn from_env(prefix: &str) -> Self {
+        Limits {
+            soft: parse_env(&format!("{prefix}_SOFT")),
+            hard: parse_env(&format!("{prefix}_HARD")),
+        }
+    }
+
+    pub fn check(&self, used: u64) -> Usage {
+        match (self.soft, self.hard) {
+            (_, Some(hard)) if used > hard => Usage::OverHardLimit,
+            (Some(soft), _) if used > soft => Usage::OverSoftLimit,
+            _ => Usage::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Usage {
+    Normal,
+    OverSoftLimit,
+    OverHardLimit,
+}
+
+/// Global daily and monthly budget of requests to a paid API shared by all replicas through Redis.
+///
+/// Configured by environment variables like `BUDGET_GOOGLE_GEOCODE_DAILY_HARD`, `BUDGET_GOOGLE_GEOCODE_MONTHLY_SOFT`
+/// and `BUDGET_GOOGLE_GEOCODE_COST` (estimated price of a single request).
+pub struct ApiBudget {
+    pool: Pool<RedisConnectionManager>,
+    provider: String,
+    api: String,
+    daily: Limits,
+    monthly: Limits,
+    cost_per_request: f64,
+
+    remaining_gauge: prometheus::GaugeVec,
+    spend_gauge: prometheus::GaugeVec,
+}
+
+impl ApiBudget {
+    /// `metric_prefix` must be unique for the provider, e.g. `google_maps_api`. All APIs of the provider share the same metrics.
+    pub fn from_env(pool: &Pool<RedisConnectionManager>, provider: &str, api: &str, metric_prefix: &str) -> Self {
+        let env_prefix = format!("{ENV_PREFIX}{}_{}", provider, api)
+            .to_uppercase()
+            .replace('-', "_");
+        let daily = Limits::from_env(&format!("{env_prefix}_DAILY"));
+        let monthly = Limits::from_env(&format!("{env_prefix}_MONTHLY"));
+        let cost_per_request = parse_env(&format!("{env_prefix}_COST")).unwrap_or(0.0);
+        tracing::info!("budget for {provider}/{api}: daily {daily:?}, monthly {monthly:?}, cost per request is {cost_per_request}");
+
+        let remaining_opts = Opts::new(format!("{metric_prefix}_budget_remaining"), "count of requests left before the hard limit is reached")
+            .const_label("API", api);
+        let spend_opts = Opts::new(format!("{metric_prefix}_estimated_spend"), "estimated spend for the current period")
+            .const_label("API", api);
+
+        ApiBudget {
+            pool: pool.clone(),
+            provider: provider.to_owned(),
+            api: api.to_owned(),
+            daily,
+            monthly,
+            cost_per_request,
+
+            remaining_gauge: metrics::REGISTRY.register_gauge_vec(&format!("{provider} {api} remaining budget"), remaining_opts, &["period"]),
+            spend_gauge: metrics::REGISTRY.register_gauge_vec(&format!("{provider} {api} estimated spend"), spend_opts, &["period"]),
+        }
+    }
+
+    /// Count a request against the budgets. Fails if any hard limit is exceeded; in that case the request is not counted.
+    /// If Redis is unavailable, the request is allowed, as well as stale lookups, which never reach the provider.
+    pub async fn acquire(&self) -> Result<(), BudgetExceeded> {
+        if is_stale_lookup() {
+            return Ok(())
+        }
+        let (daily_key, monthly_key) = self.keys();
+        let (daily_used, monthly_used) = match self.increment(&daily_key, &monthly_key, 1).await {
+            Ok(counts) => counts,
+            Err(err) => {
+                tracing::error!("couldn't check the budget for {}/{}: {err}", self.provider, self.api);
+                return Ok(())
+            }
+        };
+
+        for (period, limits, used) in [(Period::Daily, self.daily, daily_used), (Period::Monthly, self.monthly, monthly_used)] {
+            match limits.check(used) {
+                Usage::Normal => {},
+                Usage::OverSoftLimit => tracing::warn!("the soft {period} limit for {}/{} is exceeded: {used} requests", self.provider, self.api),
+                Usage::OverHardLimit => {
+                    self.release().await;
+                    return Err(BudgetExceeded {
+                        provider: self.provider.clone(),
+                        api: self.api.clone(),
+                        period,
+                    })
+                }
+            }
+        }
+
+        self.export(daily_used, monthly_used);
+        Ok(())
+    }
+
+    /// Return a request counted by `acquire` to the budgets, since it hasn't reached the provider.
+    async fn release(&self) {
+        if is_stale_lookup() {
+            return
+        }
+        let (daily_key, monthly_key) = self.keys();
+        match self.increment(&daily_key, &monthly_key, -1).await {
+            Ok((daily_used, monthly_used)) => self.export(daily_used, monthly_used),
+            Err(err) => tracing::error!("couldn't return the request to the budget for {}/{}: {err}", self.provider, self.api),
+        }
+    }
+
+    async fn increment(&self, daily_key: &str, monthly_key: &str, delta: i64) -> anyhow::Result<(u64, u64)> {
+        let mut conn = self.pool
+            .get().await?
+            .into_inner();
+        let (daily, monthly): (i64, i64) = redis::pipe().atomic()
+            .incr(daily_key, delta)
+            .expire(daily_key, DAILY_KEY_TTL_SECS).ignore()
+            .incr(monthly_key, delta)
+            .expire(monthly_key, MONTHLY_KEY_TTL_SECS).ignore()
+            .query_async(&mut conn).await?;
+        Ok((daily.max(0) as u64, monthly.max(0) as u64))
+    }
+
+    fn keys(&self) -> (String, String) {
+        let (year, month, day) = today();
+        let prefix = format!("{REDIS_KEY_PREFIX}:{}:{}", self.provider, self.api);
+        (format!("{prefix}:{year:04}-{month:02}-{day:02}"), format!("{prefix}:{year:04}-{month:02}"))
+    }
+
+    fn export(&self, daily_used: u64, monthly_used: u64) {
+        for (period, limits, used) in [(Period::Daily, self.daily, daily_used), (Period::Monthly, self.monthly, monthly_used)] {
+            if let Some(hard) = limits.hard {
+                self.remaining_gauge.with_label_values(&[period.as_ref()]).set(hard.saturating_sub(used) as f64);
+            }
+            self.spend_gauge.with_label_values(&[period.as_ref()]).set(used as f64 * self.cost_per_request);
+        }
+    }
+}
+
+/// Charges the budget attached to the request as an extension. It must be added before the cache, so that a cached
+/// response is returned even if the budget is exhausted, but only the requests that reached the provider are counted.
+pub struct BudgetMiddleware;
+
+#[async_trait]
+impl Middleware for BudgetMiddleware {
+    async fn handle(&self, req: Request, extensions: &mut Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+        let Some(budget) = extensions.get::<Arc<ApiBudget>>().cloned() else {
+            return next.run(req, extensions).await
+        };
+        let acquired = budget.acquire().await;
+        if acquired.is_err() {
+            extensions.insert(CacheMode::OnlyIfCached);
+        }
+        let resp = next.run(req, extensions).await?;
+        match acquired {
+            Ok(()) if from_cache(&resp) => budget.release().await,
+            Ok(()) => {},
+            Err(_) if from_cache(&resp) => {},
+            Err(err) => return Err(reqwest_middleware::Error::middleware(err)),
+        }
+        Ok(resp)
+    }
+}
+
+/// The error is returned by the middleware, so it comes wrapped into the error of the HTTP client.
+pub fn is_budget_exceeded(err: &anyhow::Error) -> bool {
+    err.is::<BudgetExceeded>() || matches!(err.downcast_ref::<reqwest_middleware::Error>(),
+        Some(reqwest_middleware::Error::Middleware(err)) if err.is::<BudgetExceeded>())
+}
+
+fn today() -> (i64, u32, u32) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    civil_from_days((secs / 86400) as i64)
+}
+
+/// Convert a number of days since 1970-01-01 into a (year, month, day) triple in the proleptic Gregorian calendar.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub(super) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .and_then(|v| v.parse()
+            .inspect_err(|_| tracing::error!("invalid value of {name}"))
+            .ok())
+}
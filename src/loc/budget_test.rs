@@ -0,0 +1,22 @@
+use super::budget::{civil_from_days, Limits, Usage};
+
+#[test]
+fn test_civil_from_days() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(59), (1970, 3, 1));
+    assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    assert_eq!(civil_from_days(20742), (2026, 10, 16));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+}
+
+#[test]
+fn test_limits() {
+    let limits = Limits { soft: Some(8), hard: Some(10) };
+    assert_eq!(limits.check(8), Usage::Normal);
+    assert_eq!(limits.check(9), Usage::OverSoftLimit);
+    assert_eq!(limits.check(10), Usage::OverSoftLimit);
+    assert_eq!(limits.check(11), Usage::OverHardLimit);
+
+    let unlimited = Limits::default();
+    assert_eq!(unlimited.check(u64::MAX), Usage::Normal);
+}
@@ -1,6 +1,7 @@
 use std::error::Error;
+use std::future::Future;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use async_trait::async_trait;
 use derive_more::Constructor;
 use http::Extensions;
@@ -17,22 +18,48 @@ use reqwest_tracing::TracingMiddleware;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use serde::de::DeserializeOwned;
+use super::budget::BudgetMiddleware;
+use super::credentials::ApiKeyMiddleware;
+use super::errors::is_error_response;
+use super::retry::RetryMiddleware;
+use super::throttle::QueueFullMiddleware;
+use super::Provider;
 
 const X_BODY_HASH: &str = "X-Body-Hash";
+/// Contains the version of the `Store` layout, which must be bumped on its changes, so that the entries of the previous
+/// format are missed instead of failing to deserialize.
+const CACHE_KEY_PREFIX: &str = "loc-cache:v2";
 
 const ENV_CACHE_MAX_TTL_SECS: &str = "CACHE_MAX_TTL_SECS";
+const ENV_CACHE_STALE_TTL_SECS: &str = "CACHE_STALE_TTL_SECS";
 const ENV_HTTP_CONNECT_TIMEOUT_SECS: &str = "HTTP_CONNECT_TIMEOUT_SECS";
 const ENV_HTTP_REQUEST_TIMEOUT_SECS: &str = "HTTP_REQUEST_TIMEOUT_SECS";
 
 const DEFAULT_CACHE_MAX_TTL_SECS: u64 = 86400; // 24 hours
+const DEFAULT_CACHE_STALE_TTL_SECS: u64 = 7 * 86400;
 const DEFAULT_HTTP_CONNECT_TIMEOUT_SECS: u64 = 5;
 const DEFAULT_HTTP_REQUEST_TIMEOUT_SECS: u64 = 30;
 
-pub fn caching_client(redis_pool: &Pool<RedisConnectionManager>) -> ClientWithMiddleware {
-    caching_client_builder(redis_pool).build()
+tokio::task_local! {
+    static STALE_LOOKUP: bool;
 }
 
-pub fn caching_client_builder(redis_pool: &Pool<RedisConnectionManager>) -> ClientBuilder {
+/// Run the future in the mode when requests are answered only from the cache, including the entries that are no longer fresh.
+/// Used as the fallback when the providers fail.
+pub async fn stale_lookup<F: Future>(fut: F) -> F::Output {
+    STALE_LOOKUP.scope(true, fut).await
+}
+
+pub fn is_stale_lookup() -> bool {
+    STALE_LOOKUP.try_with(|stale| *stale).unwrap_or(false)
+}
+
+pub fn caching_client(provider: Provider, redis_pool: &Pool<RedisConnectionManager>) -> ClientWithMiddleware {
+    caching_client_builder(provider, redis_pool).build()
+}
+
+/// Middlewares added to the builder run after the cache and retries, so they see only the requests to the network.
+pub fn caching_client_builder(provider: Provider, redis_pool: &Pool<RedisConnectionManager>) -> ClientBuilder {
     let connect_timeout = get_env_or_default(ENV_HTTP_CONNECT_TIMEOUT_SECS, DEFAULT_HTTP_CONNECT_TIMEOUT_SECS);
     let request_timeout = get_env_or_default(ENV_HTTP_REQUEST_TIMEOUT_SECS, DEFAULT_HTTP_REQUEST_TIMEOUT_SECS);
     let client = reqwest::Client::builder()
@@ -42,6 +69,9 @@ pub fn caching_client_builder(redis_pool: &Pool<RedisConnectionManager>) -> Clie
     ClientBuilder::new(client)
         .with(TracingMiddleware::default())
         .with(InsertBodyHashIntoHeadersMiddleware)
+        .with(StaleLookupMiddleware)
+        .with(BudgetMiddleware)
+        .with(QueueFullMiddleware)
         .with(Cache(HttpCache {
             mode: CacheMode::IgnoreRules,
             manager: RedisCacheManager::new(redis_pool.clone()),
@@ -54,11 +84,18 @@ pub fn caching_client_builder(redis_pool: &Pool<RedisConnectionManager>) -> Clie
                     let body_hash = parts.headers.get(X_BODY_HASH)
                         .and_then(|v| v.to_str().ok())
                         .unwrap_or("no-body-hash");
-                    format!("loc-cache:{}:{}:{}", parts.method, parts.uri, body_hash)
+                    format!("{CACHE_KEY_PREFIX}:{}:{}:{}", parts.method, parts.uri, body_hash)
+                })),
+                // IgnoreRules would store error payloads for CACHE_MAX_TTL_SECS otherwise
+                response_cache_mode_fn: Some(Arc::new(|_, resp| {
+                    is_error_response(resp.status, &resp.body).then_some(CacheMode::NoStore)
                 })),
                 ..HttpCacheOptions::default()
             },
         }))
+        // keys are attached after the cache lookup, so they don't get into the cache
+        .with(ApiKeyMiddleware)
+        .with(RetryMiddleware::from_env(provider))
 }
 
 struct InsertBodyHashIntoHeadersMiddleware;
@@ -77,15 +114,33 @@ impl Middleware for InsertBodyHashIntoHeadersMiddleware {
     }
 }
 
+/// Keeps stale lookups away from the network.
+struct StaleLookupMiddleware;
+
+#[async_trait]
+impl Middleware for StaleLookupMiddleware {
+    async fn handle(&self, req: Request, extensions: &mut Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+        if is_stale_lookup() {
+            extensions.insert(CacheMode::OnlyIfCached);
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// Entries are kept in Redis for `CACHE_STALE_TTL_SECS`, but only those younger than `CACHE_MAX_TTL_SECS` are fresh.
+/// Stale entries are returned for stale lookups only.
 #[derive(Clone, Constructor)]
 struct RedisCacheManager {
     pool: Pool<RedisConnectionManager>,
 }
 
+/// Serialized by postcard, which doesn't tolerate any changes of the fields; see `CACHE_KEY_PREFIX`.
 #[derive(Debug, Deserialize, Serialize)]
 struct Store {
     response: HttpResponse,
     policy: CachePolicy,
+    /// Unix time in seconds.
+    stored_at: u64,
 }
 
 #[async_trait]
@@ -100,19 +155,20 @@ impl CacheManager for RedisCacheManager {
             .and_then(|result| result
                 .inspect_err(|err| log::error!("Couldn't deserialize the record fetched from Redis: {err}"))
                 .ok())
-            .map(|store: Store| (store.response, store.policy));
+            .filter(|store: &Store| is_stale_lookup() || unix_time().saturating_sub(store.stored_at) <= max_ttl())
+            .map(|store| (store.response, store.policy));
         Ok(result)
     }
 
     async fn put(&self, cache_key: String, res: HttpResponse, policy: CachePolicy) -> http_cache::Result<HttpResponse> {
-        let store = Store { response: res.clone(), policy };
+        let store = Store { response: res.clone(), policy, stored_at: unix_time() };
         let data = serialize(&store)
             .inspect_err(|err| log::error!("Couldn't serialize the response: {err}"))?;
-        let max_ttl: u64 = get_env_or_default(ENV_CACHE_MAX_TTL_SECS, DEFAULT_CACHE_MAX_TTL_SECS);
+        let stale_ttl: u64 = get_env_or_default(ENV_CACHE_STALE_TTL_SECS, DEFAULT_CACHE_STALE_TTL_SECS);
         self.pool
             .get().await
             .inspect_err(log_failed_connection_error)?
-            .set_ex::<_, _, ()>(cache_key, data, max_ttl).await
+            .set_ex::<_, _, ()>(cache_key, data, stale_ttl.max(max_ttl())).await
             .inspect_err(|err| log::error!("Couldn't push a record into Redis: {err}"))?;
         Ok(res)
     }
@@ -140,7 +196,7 @@ pub trait WithCachedResponseCounters {
     }
 }
 
-fn from_cache(resp: &Response) -> bool {
+pub(super) fn from_cache(resp: &Response) -> bool {
     log::debug!("Response headers: {:?}", resp.headers());
 
     let hit = HitOrMiss::HIT.to_string();
@@ -154,6 +210,16 @@ fn from_cache(resp: &Response) -> bool {
         .is_some()
 }
 
+fn max_ttl() -> u64 {
+    get_env_or_default(ENV_CACHE_MAX_TTL_SECS, DEFAULT_CACHE_MAX_TTL_SECS)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
 fn log_failed_connection_error(err: &impl Error) {
     log::error!("Couldn't get a Redis connection: {err}")
 }
@@ -166,7 +232,7 @@ fn deserialize<T: DeserializeOwned>(bytes: Vec<u8>) -> Result<T, postcard::Error
     postcard::from_bytes(bytes.as_slice())
 }
 
-fn get_env_or_default<T: FromStr>(name: &str, default: T) -> T {
+pub(super) fn get_env_or_default<T: FromStr>(name: &str, default: T) -> T {
     std::env::var(name)
         .ok()
         .and_then(|v| v.parse().ok())
@@ -3,13 +3,14 @@ use http::Method;
 use reqwest::Body;
 use reqwest_middleware::ClientWithMiddleware;
 use crate::loc::cache::caching_client_builder;
+use crate::loc::Provider;
 use crate::testutils::start_redis;
 
 #[tokio::test]
 async fn test_cache() {
     let (_redis_container, redis_pool) = start_redis().await;
     let middleware = Arc::new(mock::RequestStoppingCounter::default());
-    let client = caching_client_builder(&redis_pool)
+    let client = caching_client_builder(Provider::OpenStreetMap, &redis_pool)
         .with_arc(middleware.clone())
         .build();
 
@@ -0,0 +1,158 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::Deserialize;
+use super::google::GoogleAPIMode;
+use super::yandex::YandexAPIMode;
+use super::Provider;
+
+const ENV_SEARCH_CHAIN_CONFIG: &str = "SEARCH_CHAIN_CONFIG";
+
+/// Used when no file is specified.
+const DEFAULT_CONFIG: &str = include_str!("search-chain.toml");
+
+/// Order of the finders for each country and language, and options of the finders.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchChainConfig {
+    default: Vec<Provider>,
+    #[serde(default)]
+    languages: HashMap<String, Vec<Provider>>,
+    #[serde(default)]
+    countries: HashMap<String, Vec<Provider>>,
+    #[serde(default)]
+    finders: HashMap<Provider, FinderOptions>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FinderOptions {
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    mode: Option<String>,
+    timeout_ms: Option<u64>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("couldn't read the search chain config from {0}: {1}")]
+    Read(String, std::io::Error),
+    #[error("couldn't parse the search chain config: {0}")]
+    Syntax(#[from] toml::de::Error),
+    #[error("'{0}' is not an ISO 3166-1 alpha-2 country code")]
+    InvalidCountryCode(String),
+    #[error("the '{0}' chain of the search chain config has no finders")]
+    EmptyChain(String),
+    #[error("{1:?} is listed more than once in the '{0}' chain of the search chain config")]
+    DuplicateFinder(String, Provider),
+    #[error("{0:?} has no modes, but one is set in the search chain config")]
+    UnexpectedMode(Provider),
+    #[error("'{1}' is not a valid mode of {0:?}")]
+    InvalidMode(Provider, String),
+    #[error("the timeout of {0:?} must be greater than zero")]
+    ZeroTimeout(Provider),
+}
+
+impl SearchChainConfig {
+    /// Read the file specified by the environment variable or fall back to the default chains.
+    pub fn load() -> Result<SearchChainConfig, ConfigError> {
+        match std::env::var(ENV_SEARCH_CHAIN_CONFIG).ok().filter(|path| !path.is_empty()) {
+            Some(path) => {
+                tracing::info!("{ENV_SEARCH_CHAIN_CONFIG} is {path}");
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|err| ConfigError::Read(path, err))?;
+                Self::parse(&text)
+            }
+            None => {
+                tracing::info!("{ENV_SEARCH_CHAIN_CONFIG} is not set, the default search chains are used");
+                Self::parse(DEFAULT_CONFIG)
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<SearchChainConfig, ConfigError> {
+        let config: SearchChainConfig = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid_country_code = self.countries.keys()
+            .find(|code| code.len() != 2 || !code.chars().all(|c| c.is_ascii_uppercase()));
+        if let Some(code) = invalid_country_code {
+            return Err(ConfigError::InvalidCountryCode(code.clone()))
+        }
+
+        let chains = std::iter::once(("default", &self.default))
+            .chain(self.languages.iter().map(|(lang, chain)| (lang.as_str(), chain)))
+            .chain(self.countries.iter().map(|(country, chain)| (country.as_str(), chain)));
+        for (name, chain) in chains {
+            if chain.is_empty() {
+                return Err(ConfigError::EmptyChain(name.to_owned()))
+            }
+            let mut seen = HashSet::with_capacity(chain.len());
+            if let Some(duplicate) = chain.iter().find(|provider| !seen.insert(**provider)) {
+                return Err(ConfigError::DuplicateFinder(name.to_owned(), *duplicate))
+            }
+        }
+
+        for (provider, options) in &self.finders {
+            if options.timeout_ms == Some(0) {
+                return Err(ConfigError::ZeroTimeout(*provider))
+            }
+            let Some(mode) = &options.mode else {
+                continue
+            };
+            let is_valid = match provider {
+                Provider::Google => GoogleAPIMode::from_str(mode).is_ok(),
+                Provider::Yandex => YandexAPIMode::from_str(mode).is_ok(),
+                _ => return Err(ConfigError::UnexpectedMode(*provider)),
+            };
+            if !is_valid {
+                return Err(ConfigError::InvalidMode(*provider, mode.clone()))
+            }
+        }
+        Ok(())
+    }
+
+    /// Enabled finders for countries and languages without their own chain.
+    pub fn default_chain(&self) -> Vec<Provider> {
+        self.enabled(&self.default)
+    }
+
+    /// Enabled finders for each language having its own chain.
+    pub fn language_chains(&self) -> impl Iterator<Item = (&str, Vec<Provider>)> {
+        self.languages.iter()
+            .map(|(lang, chain)| (lang.as_str(), self.enabled(chain)))
+    }
+
+    /// Enabled finders for each country having its own chain.
+    pub fn country_chains(&self) -> impl Iterator<Item = (&str, Vec<Provider>)> {
+        self.countries.iter()
+            .map(|(country, chain)| (country.as_str(), self.enabled(chain)))
+    }
+
+    /// The mode of the provider if it's set. The value has been checked by the validation already.
+    pub fn mode<M: FromStr>(&self, provider: Provider) -> Option<M> {
+        self.finders.get(&provider)?
+            .mode.as_ref()?
+            .parse().ok()
+    }
+
+    pub fn timeout(&self, provider: Provider) -> Option<Duration> {
+        self.finders.get(&provider)?
+            .timeout_ms
+            .map(Duration::from_millis)
+    }
+
+    fn enabled(&self, chain: &[Provider]) -> Vec<Provider> {
+        chain.iter()
+            .filter(|provider| self.finders.get(provider).is_none_or(|options| options.enabled))
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+use super::Provider;
+use super::config::{ConfigError, SearchChainConfig};
+use super::google::GoogleAPIMode;
+use super::yandex::YandexAPIMode;
+
+type ErrorMatcher = fn(&ConfigError) -> bool;
+
+#[test]
+fn test_default_config() {
+    let config = SearchChainConfig::load().expect("the embedded config must be valid");
+    assert_eq!(config.default_chain().first(), Some(&Provider::Google));
+    assert!(matches!(config.mode(Provider::Yandex), Some(YandexAPIMode::Geocode)));
+}
+
+#[test]
+fn test_chains_and_options() {
+    let config = SearchChainConfig::parse(r#"
+        default = ["google", "osm", "photon"]
+
+        [languages]
+        ru = ["yandex", "osm"]
+
+        [countries]
+        BY = ["yandex", "google"]
+
+        [finders.osm]
+        enabled = false
+
+        [finders.google]
+        mode = "text"
+        timeout_ms = 1500
+    "#).unwrap();
+
+    assert_eq!(config.default_chain(), vec![Provider::Google, Provider::Photon]);
+    let languages: Vec<_> = config.language_chains().collect();
+    assert_eq!(languages, vec![("ru", vec![Provider::Yandex])]);
+    let countries: Vec<_> = config.country_chains().collect();
+    assert_eq!(countries, vec![("BY", vec![Provider::Yandex, Provider::Google])]);
+    assert!(matches!(config.mode(Provider::Google), Some(GoogleAPIMode::Text)));
+    assert!(config.mode::<YandexAPIMode>(Provider::Yandex).is_none());
+    assert_eq!(config.timeout(Provider::Google), Some(Duration::from_millis(1500)));
+    assert_eq!(config.timeout(Provider::Photon), None);
+}
+
+#[test]
+fn test_invalid_configs() {
+    let cases: [(&str, ErrorMatcher); 8] = [
+        (r#"default = ["bing"]"#, |e| matches!(e, ConfigError::Syntax(_))),
+        (r#"defaults = ["google"]"#, |e| matches!(e, ConfigError::Syntax(_))),
+        (r#"default = []"#, |e| matches!(e, ConfigError::EmptyChain(_))),
+        ("default = [\"google\"]\n[countries]\nRussia = [\"yandex\"]", |e| matches!(e, ConfigError::InvalidCountryCode(_))),
+        ("default = [\"google\"]\n[languages]\nru = [\"yandex\", \"osm\", \"yandex\"]",
+            |e| matches!(e, ConfigError::DuplicateFinder(lang, Provider::Yandex) if lang == "ru")),
+        ("default = [\"osm\"]\n[finders.osm]\nmode = \"Text\"", |e| matches!(e, ConfigError::UnexpectedMode(Provider::OpenStreetMap))),
+        ("default = [\"yandex\"]\n[finders.yandex]\nmode = \"Text\"", |e| matches!(e, ConfigError::InvalidMode(Provider::Yandex, _))),
+        ("default = [\"photon\"]\n[finders.photon]\ntimeout_ms = 0", |e| matches!(e, ConfigError::ZeroTimeout(Provider::Photon))),
+    ];
+    for (text, is_expected) in cases {
+        match SearchChainConfig::parse(text) {
+            Err(err) => assert!(is_expected(&err), "unexpected error for '{text}': {err}"),
+            Ok(config) => panic!("'{text}' must be rejected, got {config:?}"),
+        }
+    }
+}
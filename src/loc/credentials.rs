@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use http::Extensions;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use reqwest_middleware::{Middleware, Next};
+
+/// A key of a paid API. It's passed to the client as an extension of the request instead of being a part of the URL,
+/// so that it never gets into cache keys, cached responses, spans and logs, and its rotation doesn't invalidate the cache.
+#[derive(Clone)]
+pub enum ApiKey {
+    /// For APIs that accept keys only in the query string.
+    QueryParam(&'static str, String),
+    Header(&'static str, String),
+}
+
+/// Attaches the `ApiKey` extension to the request right before it's sent to the network, after the cache lookup.
+/// The URL of the response and errors is restored to the one without the key, since reqwest keeps it there.
+pub(super) struct ApiKeyMiddleware;
+
+#[async_trait]
+impl Middleware for ApiKeyMiddleware {
+    async fn handle(&self, mut req: Request, extensions: &mut Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+        let Some(api_key) = extensions.get::<ApiKey>().cloned() else {
+            return next.run(req, extensions).await
+        };
+
+        let url = req.url().clone();
+        match api_key {
+            ApiKey::QueryParam(name, value) => {
+                req.url_mut().query_pairs_mut().append_pair(name, &value);
+            }
+            ApiKey::Header(name, value) => {
+                let name = HeaderName::try_from(name).map_err(reqwest_middleware::Error::middleware)?;
+                let mut value = HeaderValue::from_str(&value).map_err(reqwest_middleware::Error::middleware)?;
+                value.set_sensitive(true);
+                req.headers_mut().insert(name, value);
+            }
+        }
+
+        match next.run(req, extensions).await {
+            Ok(resp) => Ok(with_url(resp, url)),
+            Err(reqwest_middleware::Error::Reqwest(err)) => Err(reqwest_middleware::Error::Reqwest(err.with_url(url))),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn with_url(resp: Response, url: Url) -> Response {
+    let (mut parts, body) = http::Response::from(resp).into_parts();
+    let url_extension = http::Response::builder()
+        .url(url)
+        .body(())
+        .map(|resp| resp.into_parts().0.extensions)
+        .unwrap_or_default();
+    parts.extensions.extend(url_extension);
+    Response::from(http::Response::from_parts(parts, body))
+}
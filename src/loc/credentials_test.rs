@@ -0,0 +1,50 @@
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use super::credentials::{ApiKey, ApiKeyMiddleware};
+
+const SECRET: &str = "secret-key";
+
+#[tokio::test]
+async fn test_key_is_sent_but_not_exposed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    // responds with the head of the request
+    tokio::spawn(async move {
+        for _ in 0..2 {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let head = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+            let resp = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{head}", head.len());
+            socket.write_all(resp.as_bytes()).await.unwrap();
+        }
+    });
+
+    let resp = client().get(format!("http://{addr}/geocode?lang=en"))
+        .with_extension(ApiKey::QueryParam("apikey", SECRET.to_owned()))
+        .send().await.unwrap();
+    assert_eq!(resp.url().as_str(), format!("http://{addr}/geocode?lang=en"));
+    assert!(resp.text().await.unwrap().starts_with(&format!("get /geocode?lang=en&apikey={SECRET} ")));
+
+    let resp = client().get(format!("http://{addr}/search"))
+        .with_extension(ApiKey::Header("X-Goog-Api-Key", SECRET.to_owned()))
+        .send().await.unwrap();
+    assert!(resp.text().await.unwrap().contains(&format!("x-goog-api-key: {SECRET}")));
+}
+
+#[tokio::test]
+async fn test_key_is_not_exposed_in_errors() {
+    let err = client().get("http://127.0.0.1:1/geocode?lang=en")
+        .with_extension(ApiKey::QueryParam("apikey", SECRET.to_owned()))
+        .send().await.unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("127.0.0.1:1/geocode?lang=en"), "{message}");
+    assert!(!message.contains(SECRET), "{message}");
+}
+
+fn client() -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(ApiKeyMiddleware)
+        .build()
+}
@@ -0,0 +1,97 @@
+use reqwest::StatusCode;
+use super::Provider;
+
+/// Longer bodies are cut in messages, since an error page may be an entire HTML document.
+const MAX_MESSAGE_LENGTH: usize = 200;
+
+/// Classes of errors reported by providers, distinguished by the way the search chain reacts to them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Limits of the account are exhausted, so the provider is useless until they're reset.
+    QuotaExceeded,
+    /// The key is invalid, revoked or not allowed to use the API. Someone has to fix the configuration.
+    AuthFailure,
+    /// The provider rejected this particular request, but other ones may succeed.
+    InvalidRequest,
+    /// The provider is unavailable for a while.
+    Transient,
+}
+
+impl ErrorClass {
+    /// `None` for successful responses.
+    pub fn from_http_status(status: StatusCode) -> Option<ErrorClass> {
+        let class = match status {
+            _ if status.is_success() => return None,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::PAYMENT_REQUIRED => ErrorClass::QuotaExceeded,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorClass::AuthFailure,
+            StatusCode::REQUEST_TIMEOUT => ErrorClass::Transient,
+            _ if status.is_client_error() => ErrorClass::InvalidRequest,
+            _ => ErrorClass::Transient,
+        };
+        Some(class)
+    }
+
+    /// The `status` field of the Google Maps Geocoding API, which reports errors with the 200 code.
+    /// `None` for `OK`, `ZERO_RESULTS` and unknown values.
+    pub fn from_google_status(status: &str) -> Option<ErrorClass> {
+        match status {
+            "OVER_QUERY_LIMIT" | "OVER_DAILY_LIMIT" => Some(ErrorClass::QuotaExceeded),
+            "REQUEST_DENIED" => Some(ErrorClass::AuthFailure),
+            "INVALID_REQUEST" => Some(ErrorClass::InvalidRequest),
+            "UNKNOWN_ERROR" => Some(ErrorClass::Transient),
+            _ => None
+        }
+    }
+
+    /// The class of a provider error, or `None` for other errors, like network failures.
+    pub fn of(err: &anyhow::Error) -> Option<ErrorClass> {
+        err.downcast_ref::<ProviderError>().map(|err| err.class)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{provider:?} responded with an error ({class:?}): {message}")]
+pub struct ProviderError {
+    pub provider: Provider,
+    pub class: ErrorClass,
+    pub message: String,
+}
+
+impl ProviderError {
+    pub fn new(provider: Provider, class: ErrorClass, message: impl Into<String>) -> ProviderError {
+        ProviderError { provider, class, message: message.into() }
+    }
+}
+
+/// Pass successful responses through and turn the others into errors with the message taken from the body.
+pub(super) async fn check_response(provider: Provider, resp: reqwest::Response) -> Result<reqwest::Response, ProviderError> {
+    let status = resp.status();
+    let Some(class) = ErrorClass::from_http_status(status) else {
+        return Ok(resp)
+    };
+    let body = resp.text().await.unwrap_or_default();
+    let message = extract_message(&body).unwrap_or_else(|| status.to_string());
+    Err(ProviderError::new(provider, class, message))
+}
+
+/// Whether the response carries an error, even if its HTTP code is 200. Such responses must never be cached.
+pub(super) fn is_error_response(status: u16, body: &[u8]) -> bool {
+    let is_success = StatusCode::from_u16(status).is_ok_and(|status| status.is_success());
+    !is_success || serde_json::from_slice::<serde_json::Value>(body).ok()
+        .and_then(|json| json["status"].as_str().and_then(ErrorClass::from_google_status))
+        .is_some()
+}
+
+/// Google puts the message into `error_message` or `error.message`, Yandex into `message`, Nominatim into `error`.
+pub(super) fn extract_message(body: &str) -> Option<String> {
+    let message = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => [&json["error_message"], &json["error"]["message"], &json["message"], &json["error"]]
+            .into_iter()
+            .find_map(|v| v.as_str())
+            .map(str::to_owned),
+        Err(_) => Some(body.trim().to_owned()),
+    };
+    message
+        .filter(|msg| !msg.is_empty())
+        .map(|msg| msg.chars().take(MAX_MESSAGE_LENGTH).collect())
+}
@@ -0,0 +1,43 @@
+use reqwest::StatusCode;
+use super::errors::{extract_message, is_error_response, ErrorClass};
+
+#[test]
+fn test_error_class_from_http_status() {
+    assert_eq!(ErrorClass::from_http_status(StatusCode::OK), None);
+    assert_eq!(ErrorClass::from_http_status(StatusCode::TOO_MANY_REQUESTS), Some(ErrorClass::QuotaExceeded));
+    assert_eq!(ErrorClass::from_http_status(StatusCode::FORBIDDEN), Some(ErrorClass::AuthFailure));
+    assert_eq!(ErrorClass::from_http_status(StatusCode::BAD_REQUEST), Some(ErrorClass::InvalidRequest));
+    assert_eq!(ErrorClass::from_http_status(StatusCode::REQUEST_TIMEOUT), Some(ErrorClass::Transient));
+    assert_eq!(ErrorClass::from_http_status(StatusCode::SERVICE_UNAVAILABLE), Some(ErrorClass::Transient));
+}
+
+#[test]
+fn test_error_class_from_google_status() {
+    assert_eq!(ErrorClass::from_google_status("OK"), None);
+    assert_eq!(ErrorClass::from_google_status("ZERO_RESULTS"), None);
+    assert_eq!(ErrorClass::from_google_status("OVER_QUERY_LIMIT"), Some(ErrorClass::QuotaExceeded));
+    assert_eq!(ErrorClass::from_google_status("REQUEST_DENIED"), Some(ErrorClass::AuthFailure));
+    assert_eq!(ErrorClass::from_google_status("INVALID_REQUEST"), Some(ErrorClass::InvalidRequest));
+    assert_eq!(ErrorClass::from_google_status("UNKNOWN_ERROR"), Some(ErrorClass::Transient));
+}
+
+#[test]
+fn test_is_error_response() {
+    assert!(!is_error_response(200, br#"{"results": [], "status": "ZERO_RESULTS"}"#));
+    assert!(!is_error_response(200, b"[]"));
+    assert!(is_error_response(200, br#"{"results": [], "status": "OVER_QUERY_LIMIT"}"#));
+    assert!(is_error_response(429, b"Too Many Requests"));
+    assert!(is_error_response(500, b""));
+}
+
+#[test]
+fn test_extract_message() {
+    assert_eq!(extract_message(r#"{"status": "REQUEST_DENIED", "error_message": "The provided API key is invalid."}"#).as_deref(),
+               Some("The provided API key is invalid."));
+    assert_eq!(extract_message(r#"{"error": {"code": 403, "message": "Permission denied"}}"#).as_deref(), Some("Permission denied"));
+    assert_eq!(extract_message(r#"{"statusCode": 403, "error": "Forbidden", "message": "Invalid key"}"#).as_deref(), Some("Invalid key"));
+    assert_eq!(extract_message(r#"{"error": "Unable to geocode"}"#).as_deref(), Some("Unable to geocode"));
+    assert_eq!(extract_message(" Bandwidth limit exceeded\n").as_deref(), Some("Bandwidth limit exceeded"));
+    assert_eq!(extract_message("").as_deref(), None);
+    assert_eq!(extract_message(&"x".repeat(1000)).map(|msg| msg.len()), Some(200));
+}
@@ -0,0 +1,63 @@
+use super::{AddressComponents, Location};
+
+/// Languages whose addresses are written from the largest part to the smallest one: "город, улица, дом".
+const LARGE_TO_SMALL_LANGUAGES: [&str; 4] = ["ru", "uk", "be", "kk"];
+
+/// A short representation of the address: the most specific part as the title and the rest of it as the description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormattedAddress {
+    pub title: String,
+    pub description: Option<String>,
+    /// The whole address in one line in the order of the locale.
+    pub line: String,
+}
+
+/// Build the title and the description from the address components. If the provider returned no components,
+/// the raw address is used as the title. `None` is returned for places without any address at all.
+pub fn format(location: &Location, lang_code: &str) -> Option<FormattedAddress> {
+    let language = lang_code.split(['-', '_']).next().unwrap_or_default();
+    let large_to_small = LARGE_TO_SMALL_LANGUAGES.contains(&language);
+    let mut parts = components_from_small_to_large(&location.components, large_to_small);
+    if parts.is_empty() {
+        let title = location.address.clone()?;
+        return Some(FormattedAddress { line: title.clone(), title, description: None })
+    }
+
+    let title = parts.remove(0);
+    let has_name = location.components.name.is_some();
+    if large_to_small {
+        parts.reverse();
+    }
+    let description = Some(parts.join(", ")).filter(|d| !d.is_empty());
+    // the name of a place always goes first, since it's not a part of the address
+    let line = match &description {
+        Some(description) if large_to_small && !has_name => format!("{description}, {title}"),
+        Some(description) => format!("{title}, {description}"),
+        None => title.clone(),
+    };
+    Some(FormattedAddress { title, description, line })
+}
+
+/// Name, street with the house number, city, region and country without repetitions (like Moscow the city and Moscow the region).
+fn components_from_small_to_large(components: &AddressComponents, large_to_small: bool) -> Vec<String> {
+    let street = match (&components.street, &components.house) {
+        (Some(street), Some(house)) if large_to_small => Some(format!("{street}, {house}")),
+        (Some(street), Some(house)) => Some(format!("{house} {street}")),
+        (street, _) => street.clone(),
+    };
+    let candidates = [
+        components.name.clone(),
+        street,
+        components.city.clone(),
+        components.region.clone(),
+        components.country.clone(),
+    ];
+
+    let mut parts: Vec<String> = Vec::with_capacity(candidates.len());
+    for part in candidates.into_iter().flatten() {
+        if !part.is_empty() && !parts.contains(&part) {
+            parts.push(part);
+        }
+    }
+    parts
+}
@@ -0,0 +1,85 @@
+use super::{AddressComponents, Location};
+use super::formatter::{format, FormattedAddress};
+
+#[test]
+fn test_house_in_english() {
+    let formatted = format(&house(), "en").unwrap();
+    assert_eq!(formatted, FormattedAddress {
+        title: "7 Tverskaya Street".to_string(),
+        description: Some("Moscow, Russia".to_string()),
+        line: "7 Tverskaya Street, Moscow, Russia".to_string(),
+    });
+}
+
+#[test]
+fn test_house_in_russian() {
+    let formatted = format(&house(), "ru").unwrap();
+    assert_eq!(formatted, FormattedAddress {
+        title: "Tverskaya Street, 7".to_string(),
+        description: Some("Russia, Moscow".to_string()),
+        line: "Russia, Moscow, Tverskaya Street, 7".to_string(),
+    });
+}
+
+#[test]
+fn test_named_place_goes_first() {
+    let location = Location {
+        components: AddressComponents {
+            name: Some("GUM".to_string()),
+            city: Some("Moscow".to_string()),
+            street: Some("Red Square".to_string()),
+            house: Some("3".to_string()),
+            ..AddressComponents::default()
+        },
+        ..Location::new(55.7547, 37.6216)
+    };
+
+    let formatted = format(&location, "ru-RU").unwrap();
+    assert_eq!(formatted.title, "GUM");
+    assert_eq!(formatted.description.as_deref(), Some("Moscow, Red Square, 3"));
+    assert_eq!(formatted.line, "GUM, Moscow, Red Square, 3");
+}
+
+#[test]
+fn test_country_has_no_description() {
+    let location = Location {
+        components: AddressComponents {
+            country: Some("Russia".to_string()),
+            ..AddressComponents::default()
+        },
+        ..Location::new(61.5, 105.3)
+    };
+
+    let formatted = format(&location, "en").unwrap();
+    assert_eq!(formatted.title, "Russia");
+    assert_eq!(formatted.description, None);
+}
+
+#[test]
+fn test_fallback_to_raw_address() {
+    let location = Location {
+        address: Some("Tverskaya St, 7, Moscow".to_string()),
+        ..Location::new(55.7577, 37.6115)
+    };
+
+    let formatted = format(&location, "en").unwrap();
+    assert_eq!(formatted.title, "Tverskaya St, 7, Moscow");
+    assert_eq!(formatted.description, None);
+
+    assert_eq!(format(&Location::new(55.7577, 37.6115), "en"), None);
+}
+
+fn house() -> Location {
+    Location {
+        address: Some("Russia, Moscow, Tverskaya Street, 7".to_string()),
+        components: AddressComponents {
+            country: Some("Russia".to_string()),
+            region: Some("Moscow".to_string()),
+            city: Some("Moscow".to_string()),
+            street: Some("Tverskaya Street".to_string()),
+            house: Some("7".to_string()),
+            ..AddressComponents::default()
+        },
+        ..Location::new(55.7577, 37.6115)
+    }
+}
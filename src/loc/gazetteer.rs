@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use async_trait::async_trait;
+use super::{AddressComponents, LocFinder, LocResult, Location, PlaceKind, Provider};
+
+const ENV_GAZETTEER_PATH: &str = "GAZETTEER_PATH";
+const ENV_GAZETTEER_ALT_NAMES_PATH: &str = "GAZETTEER_ALT_NAMES_PATH";
+
+const RESULTS_LIMIT: usize = 10;
+/// Only populated places and administrative divisions are loaded; other features are too numerous in `allCountries`.
+const FEATURE_CLASSES: [&str; 2] = ["P", "A"];
+/// How many times the population must be bigger to outweigh a worse match (e^5 ≈ 150).
+const MATCH_QUALITY_WEIGHT: f64 = 5.0;
+/// Typos are corrected only in names sharing the beginning of the query, otherwise the fuzzy pass would go through
+/// a good part of the index for every query.
+const FUZZY_PREFIX_LEN: usize = 2;
+
+/// Offline finder searching through a GeoNames dump (`cities*.txt` or `allCountries.txt`) loaded into memory at startup.
+/// Localized names are taken from the optional `alternateNamesV2.txt` file.
+pub struct GazetteerLocFinder(Arc<Gazetteer>);
+
+struct Gazetteer {
+    places: Vec<Place>,
+    /// Normalized names sorted alphabetically with indices of the places.
+    index: Vec<(String, usize)>,
+}
+
+struct Place {
+    id: u64,
+    kind: PlaceKind,
+    name: String,
+    country_code: String,
+    latitude: f64,
+    longitude: f64,
+    population: u64,
+    localized_names: HashMap<String, String>,
+}
+
+impl GazetteerLocFinder {
+    /// Returns `None` if the path to the dump is not set or the dump cannot be loaded.
+    pub fn from_env() -> Option<GazetteerLocFinder> {
+        let path = std::env::var(ENV_GAZETTEER_PATH).ok().filter(|v| !v.is_empty())?;
+        let alt_names_path = std::env::var(ENV_GAZETTEER_ALT_NAMES_PATH).ok().filter(|v| !v.is_empty());
+        match Self::load(&path, alt_names_path.as_deref()) {
+            Ok(finder) => {
+                tracing::info!("{} places and {} names were loaded into the gazetteer from {path}", finder.0.places.len(), finder.0.index.len());
+                Some(finder)
+            }
+            Err(err) => {
+                tracing::error!("couldn't load the gazetteer from {path}: {err}");
+                None
+            }
+        }
+    }
+
+    fn load(path: &str, alt_names_path: Option<&str>) -> std::io::Result<GazetteerLocFinder> {
+        let places = BufReader::new(File::open(path)?);
+        let alt_names = alt_names_path
+            .map(File::open)
+            .transpose()?
+            .map(BufReader::new);
+        Self::parse(places, alt_names)
+    }
+
+    pub(super) fn parse(places_reader: impl BufRead, alt_names_reader: Option<impl BufRead>) -> std::io::Result<GazetteerLocFinder> {
+        let mut places = Vec::new();
+        let mut ids = HashMap::new();
+        let mut index = Vec::new();
+
+        for line in places_reader.lines() {
+            let line = line?;
+            let Some((id, place, names)) = parse_place(&line) else {
+                continue
+            };
+            let idx = places.len();
+            index.extend(names.into_iter().map(|name| (name, idx)));
+            ids.insert(id, idx);
+            places.push(place);
+        }
+
+        if let Some(reader) = alt_names_reader {
+            for line in reader.lines() {
+                let line = line?;
+                let Some((id, lang, name, preferred)) = parse_alt_name(&line) else {
+                    continue
+                };
+                let Some(&idx) = ids.get(&id) else {
+                    continue
+                };
+                index.push((normalize(name), idx));
+                let localized = &mut places[idx].localized_names;
+                if preferred || !localized.contains_key(lang) {
+                    localized.insert(lang.to_owned(), name.to_owned());
+                }
+            }
+        }
+
+        index.sort_unstable();
+        index.dedup();
+        Ok(GazetteerLocFinder(Arc::new(Gazetteer { places, index })))
+    }
+}
+
+impl Gazetteer {
+    fn search(&self, query: &str, lang_code: &str) -> Vec<Location> {
+        // "City, Country" queries are matched by the name of the city only
+        let query = normalize(query.split(',').next().unwrap_or_default());
+        if query.is_empty() {
+            return Vec::default()
+        }
+
+        let mut best_matches: HashMap<usize, u32> = HashMap::new();
+        for (name, idx) in self.names_with_prefix(&query) {
+            add_match(&mut best_matches, *idx, if *name == query { 0 } else { 1 });
+        }
+
+        let max_typos = max_typos(&query);
+        if best_matches.len() < RESULTS_LIMIT && max_typos > 0 {
+            let query_len = query.chars().count();
+            let prefix: String = query.chars().take(FUZZY_PREFIX_LEN).collect();
+            for (name, idx) in self.names_with_prefix(&prefix) {
+                if name.chars().count().abs_diff(query_len) > max_typos {
+                    continue
+                }
+                let distance = levenshtein(name, &query);
+                if distance <= max_typos {
+                    add_match(&mut best_matches, *idx, 1 + distance as u32);
+                }
+            }
+        }
+
+        let mut matches: Vec<(usize, f64)> = best_matches.into_iter()
+            .map(|(idx, quality)| {
+                let population = self.places[idx].population as f64;
+                (idx, population.ln_1p() - MATCH_QUALITY_WEIGHT * f64::from(quality))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+        matches.into_iter()
+            .take(RESULTS_LIMIT)
+            .map(|(idx, _)| self.places[idx].to_location(lang_code))
+            .collect()
+    }
+
+    fn names_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a (String, usize)> + 'a {
+        let start = self.index.partition_point(|(name, _)| name.as_str() < prefix);
+        self.index[start..].iter()
+            .take_while(move |(name, _)| name.starts_with(prefix))
+    }
+}
+
+#[async_trait]
+impl LocFinder for GazetteerLocFinder {
+    #[tracing::instrument(skip(self))]
+    async fn find(&self, query: &str, lang_code: &str, _location: Option<(f64, f64)>) -> LocResult {
+        // the fuzzy pass is CPU-bound and mustn't block the workers of the runtime
+        let gazetteer = self.0.clone();
+        let (query, lang_code) = (query.to_owned(), lang_code.to_owned());
+        let results = tokio::task::spawn_blocking(move || gazetteer.search(&query, &lang_code)).await?;
+        Ok(results)
+    }
+}
+
+impl Place {
+    fn to_location(&self, lang_code: &str) -> Location {
+        let name = self.localized_names.get(lang_code).unwrap_or(&self.name);
+        let address = if self.country_code.is_empty() {
+            name.clone()
+        } else {
+            format!("{name}, {}", self.country_code)
+        };
+        let components = AddressComponents {
+            country: Some(self.country_code.clone()).filter(|cc| !cc.is_empty()),
+            region: Some(name.clone()).filter(|_| self.kind == PlaceKind::Region),
+            city: Some(name.clone()).filter(|_| self.kind == PlaceKind::City),
+            ..AddressComponents::default()
+        };
+        Location {
+            address: Some(address),
+            provider: Some(Provider::Gazetteer),
+            place_id: Some(self.id.to_string()),
+            kind: self.kind,
+            components,
+            ..Location::new(self.latitude, self.longitude)
+        }
+    }
+}
+
+/// Parse a row of the `geoname` table: geonameid, name, asciiname, alternatenames, latitude, longitude,
+/// feature class, feature code, country code, cc2, admin1-4 codes, population, ...
+fn parse_place(line: &str) -> Option<(u64, Place, Vec<String>)> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 15 || !FEATURE_CLASSES.contains(&columns[6]) {
+        return None
+    }
+    let id = columns[0].parse().ok()?;
+    let kind = match (columns[6], columns[7]) {
+        ("P", _) => PlaceKind::City,
+        (_, code) if code.starts_with("PCL") => PlaceKind::Country,
+        _ => PlaceKind::Region,
+    };
+    let place = Place {
+        id,
+        kind,
+        name: columns[1].to_owned(),
+        country_code: columns[8].to_owned(),
+        latitude: columns[4].parse().ok()?,
+        longitude: columns[5].parse().ok()?,
+        population: columns[14].parse().unwrap_or_default(),
+        localized_names: HashMap::new(),
+    };
+    let names = [columns[1], columns[2]].into_iter()
+        .chain(columns[3].split(','))
+        .map(normalize)
+        .filter(|name| !name.is_empty())
+        .collect();
+    Some((id, place, names))
+}
+
+/// Parse a row of the `alternatename` table: alternateNameId, geonameid, isolanguage, alternate name, isPreferredName, ...
+/// Pseudo-languages like `link`, `post` or `iata` are skipped.
+fn parse_alt_name(line: &str) -> Option<(u64, &str, &str, bool)> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 4 || columns[2].len() != 2 || columns[3].is_empty() {
+        return None
+    }
+    let id = columns[1].parse().ok()?;
+    let preferred = columns.get(4) == Some(&"1");
+    Some((id, columns[2], columns[3], preferred))
+}
+
+/// Keep the best quality (the lower, the better) of all names of the place that matched the query.
+fn add_match(matches: &mut HashMap<usize, u32>, idx: usize, quality: u32) {
+    matches.entry(idx)
+        .and_modify(|q| *q = (*q).min(quality))
+        .or_insert(quality);
+}
+
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+        .replace('ё', "е")
+}
+
+fn max_typos(query: &str) -> usize {
+    match query.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+pub(super) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
@@ -0,0 +1,55 @@
+use super::gazetteer::{levenshtein, GazetteerLocFinder};
+use super::LocFinder;
+
+const PLACES: &str = "\
+2988507\tParis\tParis\tLutetia,Paris,Parizh\t48.85341\t2.3488\tP\tPPLC\tFR\t\t11\t75\t751\t75056\t2138551\t\t42\tEurope/Paris\t2024-01-01
+4717560\tParis\tParis\t\t33.66094\t-95.55551\tP\tPPLA2\tUS\t\tTX\t277\t\t\t24171\t\t183\tAmerica/Chicago\t2024-01-01
+3172394\tParma\tParma\t\t44.79935\t10.32618\tP\tPPLA2\tIT\t\t45\tPR\t034027\t\t146299\t\t55\tEurope/Rome\t2024-01-01
+524901\tMoscow\tMoscow\tMoskva\t55.75222\t37.61556\tP\tPPLC\tRU\t\t48\t\t\t\t10381222\t\t144\tEurope/Moscow\t2024-01-01
+2993838\tParis Creek\tParis Creek\t\t48.1\t2.1\tH\tSTM\tFR\t\t\t\t\t\t0\t\t\t\t2024-01-01
+";
+
+const ALT_NAMES: &str = "\
+1\t524901\tru\tМосква\t1\t\t\t\t\t
+2\t524901\tlink\thttps://en.wikipedia.org/wiki/Moscow\t\t\t\t\t\t
+3\t2988507\tru\tПариж\t\t\t\t\t\t
+";
+
+fn gazetteer() -> GazetteerLocFinder {
+    GazetteerLocFinder::parse(PLACES.as_bytes(), Some(ALT_NAMES.as_bytes()))
+        .expect("the test data must be parsed")
+}
+
+async fn addresses(finder: &GazetteerLocFinder, query: &str, lang_code: &str) -> Vec<String> {
+    finder.find(query, lang_code, None).await
+        .expect("the gazetteer never fails")
+        .into_iter()
+        .filter_map(|loc| loc.address())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_population_weighted_ranking() {
+    let finder = gazetteer();
+    assert_eq!(addresses(&finder, "Paris", "en").await, vec!["Paris, FR", "Paris, US"]);
+    assert_eq!(addresses(&finder, "par", "en").await, vec!["Paris, FR", "Parma, IT", "Paris, US"]);
+}
+
+#[tokio::test]
+async fn test_localized_and_fuzzy_names() {
+    let finder = gazetteer();
+    assert_eq!(addresses(&finder, "москва", "ru").await, vec!["Москва, RU"]);
+    assert_eq!(addresses(&finder, "Moskva", "en").await, vec!["Moscow, RU"]);
+    assert_eq!(addresses(&finder, "Mosvow, Russia", "en").await, vec!["Moscow, RU"]);
+    assert!(addresses(&finder, "Mpscow", "en").await.is_empty(), "typos are corrected only after the prefix");
+    assert_eq!(addresses(&finder, "Париж", "ru").await, vec!["Париж, FR"]);
+    assert!(addresses(&finder, "Paris Creek", "en").await.is_empty());
+}
+
+#[test]
+fn test_levenshtein() {
+    assert_eq!(levenshtein("moscow", "moscow"), 0);
+    assert_eq!(levenshtein("moscow", "mosvow"), 1);
+    assert_eq!(levenshtein("moscow", "moskva"), 3);
+    assert_eq!(levenshtein("", "abc"), 3);
+}
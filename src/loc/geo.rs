@@ -0,0 +1,31 @@
+use super::BoundingBox;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Great-circle distance between two `(latitude, longitude)` points in meters (the haversine formula).
+pub fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+
+    let d_lat = lat2 - lat1;
+    let d_lon = lon2 - lon1;
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * h.sqrt().asin()
+}
+
+/// The smallest box containing the circle of the radius in meters around the `(latitude, longitude)` point.
+/// The box is widened to the full range of longitudes near the poles.
+pub fn bounding_box(center: (f64, f64), radius: f64) -> BoundingBox {
+    let (lat, lon) = center;
+    let d_lat = (radius / EARTH_RADIUS_METERS).to_degrees();
+    let d_lon = match lat.to_radians().cos() {
+        cos if cos > f64::EPSILON => (d_lat / cos).min(180.0),
+        _ => 180.0,
+    };
+    BoundingBox {
+        south: (lat - d_lat).max(-90.0),
+        north: (lat + d_lat).min(90.0),
+        west: (lon - d_lon).max(-180.0),
+        east: (lon + d_lon).min(180.0),
+    }
+}
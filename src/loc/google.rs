@@ -1,42 +1,61 @@
-use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 use async_trait::async_trait;
-use once_cell::sync::Lazy;
 use reqwest_middleware::ClientWithMiddleware;
 use strum_macros::EnumString;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use super::budget::{is_budget_exceeded, ApiBudget};
 use super::cache::WithCachedResponseCounters;
-use super::{cache, Location, LocFinder, LocResult, SEARCH_RADIUS, SearchParams};
+use super::credentials::ApiKey;
+use super::errors::{check_response, ErrorClass, ProviderError};
+use super::keys::KeyPool;
+use super::nearby::{self, Category, NearbyLocFinder};
+use super::retry::SafeToRetry;
+use super::reverse::ReverseLocFinder;
+use super::suggest::{session_token, Suggestion, SuggestionResolver};
+use super::{cache, response, AddressComponents, BoundingBox, Location, LocFinder, LocResult, PlaceKind, Provider, SEARCH_RADIUS, SearchParams};
 use crate::metrics;
 use crate::redis::REDIS;
 
 const FINDER_ENV_API_KEY: &str = "GOOGLE_MAPS_API_KEY";
+/// Replaced by the mode in the search chain config.
+const OBSOLETE_ENV_MODE: &str = "GAPI_MODE";
 
-static GAPI_MODE: Lazy<GoogleAPIMode> = Lazy::new(|| {
-    let val = std::env::var("GAPI_MODE").expect("GAPI_MODE must be set!");
-    tracing::info!("GAPI_MODE is {val}");
-    GoogleAPIMode::from_str(val.as_str()).expect("Invalid value of GAPI_MODE")
-});
-
-#[derive(EnumString)]
+#[derive(EnumString, Debug, Default, Copy, Clone)]
+#[strum(ascii_case_insensitive)]
 pub enum GoogleAPIMode {
     Text,       // Text Search request
+    #[default]
     GeoText,    // Geocoding request first, Text Search if ZERO_RESULTS
-}
-
-/// Load and check required parameters at startup
-pub fn preload_env_vars() {
-    let _ = *GAPI_MODE;
+    Autocomplete, // Place Autocomplete suggestions; Place Details are requested only for the chosen one
 }
 
 pub struct GoogleLocFinder {
     client: ClientWithMiddleware,
-    api_key: String,
+    mode: RwLock<GoogleAPIMode>,
+
+    geocode_keys: KeyPool,
+    reverse_geocode_keys: KeyPool,
+    text_keys: KeyPool,
+    autocomplete_keys: KeyPool,
+    details_keys: KeyPool,
+    nearby_keys: KeyPool,
 
     geocode_req_counter: prometheus::Counter,
+    reverse_geocode_req_counter: prometheus::Counter,
     text_req_counter: prometheus::Counter,
+    autocomplete_req_counter: prometheus::Counter,
+    details_req_counter: prometheus::Counter,
+    nearby_req_counter: prometheus::Counter,
     cached_resp_counter: prometheus::Counter,
     fetched_resp_counter: prometheus::Counter,
+
+    geocode_budget: Arc<ApiBudget>,
+    reverse_geocode_budget: Arc<ApiBudget>,
+    text_budget: Arc<ApiBudget>,
+    autocomplete_budget: Arc<ApiBudget>,
+    details_budget: Arc<ApiBudget>,
+    nearby_budget: Arc<ApiBudget>,
 }
 
 #[derive(Serialize)]
@@ -67,35 +86,128 @@ impl SearchQuery {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all="camelCase")]
+struct AutocompleteQuery {
+    input: String,
+    language_code: String,
+    location_bias: Option<serde_json::Value>,
+    /// The distances to the suggestions are returned only if the origin is set.
+    origin: Option<serde_json::Value>,
+    session_token: Option<String>,
+}
+
+impl AutocompleteQuery {
+    fn new(input: &str, lang_code: &str, location: Option<(f64, f64)>, session_token: Option<String>) -> Self {
+        let SearchQuery { location_bias, .. } = SearchQuery::new(input, lang_code, location);
+        Self {
+            input: input.to_string(),
+            language_code: lang_code.to_string(),
+            location_bias,
+            origin: location.map(|(lat, lng)| json!({
+                "latitude": lat,
+                "longitude": lng
+            })),
+            session_token,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all="camelCase")]
+struct NearbyQuery {
+    included_types: &'static [&'static str],
+    language_code: String,
+    location_restriction: serde_json::Value,
+    rank_preference: &'static str,
+}
+
+impl NearbyQuery {
+    fn new(category: Category, lang_code: &str, (lat, lng): (f64, f64), radius: f64) -> Self {
+        Self {
+            included_types: map_category(category),
+            language_code: lang_code.to_string(),
+            location_restriction: json!({
+                "circle": {
+                    "center": {
+                        "latitude": lat,
+                        "longitude": lng
+                    },
+                    "radius": radius
+                }
+            }),
+            rank_preference: "DISTANCE",
+        }
+    }
+}
+
 impl GoogleLocFinder {
-    pub fn init(api_key: &str) -> GoogleLocFinder {
+    pub fn init(keys: KeyPool) -> GoogleLocFinder {
         let base_opts = prometheus::Opts::new("google_maps_api_requests_total", "count of requests to the Google Maps API");
         let geocode_opts = base_opts.clone().const_label("API", "geocode");
+        let reverse_geocode_opts = base_opts.clone().const_label("API", "reverse-geocode");
         let text_opts    = base_opts.clone().const_label("API", "place-text");
+        let autocomplete_opts = base_opts.clone().const_label("API", "place-autocomplete");
+        let details_opts = base_opts.clone().const_label("API", "place-details");
+        let nearby_opts  = base_opts.clone().const_label("API", "place-nearby");
 
         let resp_opts = prometheus::Opts::new("google_maps_api_responses_total", "count of responses from the Google Maps API split by the source");
         let from_cache_opts = resp_opts.clone().const_label("source", "cache");
         let from_remote_opts = resp_opts.const_label("source", "remote");
 
         GoogleLocFinder {
-            client: cache::caching_client(&REDIS.pool),
-            api_key: api_key.to_string(),
+            client: cache::caching_client(Provider::Google, &REDIS.pool),
+            mode: RwLock::default(),
+
+            reverse_geocode_keys: keys.for_another_api(),
+            text_keys:            keys.for_another_api(),
+            autocomplete_keys:    keys.for_another_api(),
+            details_keys:         keys.for_another_api(),
+            nearby_keys:          keys.for_another_api(),
+            geocode_keys:         keys,
 
             geocode_req_counter: metrics::REGISTRY.register_counter("Google Maps API (geocode) requests", geocode_opts),
+            reverse_geocode_req_counter: metrics::REGISTRY.register_counter("Google Maps API (reverse geocode) requests", reverse_geocode_opts),
             text_req_counter:    metrics::REGISTRY.register_counter("Google Maps API (place, text) requests", text_opts),
+            autocomplete_req_counter: metrics::REGISTRY.register_counter("Google Maps API (place, autocomplete) requests", autocomplete_opts),
+            details_req_counter: metrics::REGISTRY.register_counter("Google Maps API (place, details) requests", details_opts),
+            nearby_req_counter:  metrics::REGISTRY.register_counter("Google Maps API (place, nearby) requests", nearby_opts),
             cached_resp_counter:  metrics::REGISTRY.register_counter("Google Maps API requests", from_cache_opts),
             fetched_resp_counter: metrics::REGISTRY.register_counter("Google Maps API requests", from_remote_opts),
+
+            geocode_budget:         Arc::new(ApiBudget::from_env(&REDIS.pool, "google", "geocode", "google_maps_api")),
+            reverse_geocode_budget: Arc::new(ApiBudget::from_env(&REDIS.pool, "google", "reverse-geocode", "google_maps_api")),
+            text_budget:            Arc::new(ApiBudget::from_env(&REDIS.pool, "google", "place-text", "google_maps_api")),
+            autocomplete_budget:    Arc::new(ApiBudget::from_env(&REDIS.pool, "google", "place-autocomplete", "google_maps_api")),
+            details_budget:         Arc::new(ApiBudget::from_env(&REDIS.pool, "google", "place-details", "google_maps_api")),
+            nearby_budget:          Arc::new(ApiBudget::from_env(&REDIS.pool, "google", "place-nearby", "google_maps_api")),
         }
     }
 
     pub fn from_env() -> GoogleLocFinder {
-        let api_key = std::env::var(FINDER_ENV_API_KEY).expect("Google Maps API key is required!");
-        Self::init(api_key.as_str())
+        let keys = KeyPool::from_env(Provider::Google, FINDER_ENV_API_KEY).expect("Google Maps API key is required!");
+        if std::env::var(OBSOLETE_ENV_MODE).is_ok() {
+            tracing::error!("{OBSOLETE_ENV_MODE} is ignored, set the mode of the google finder in the search chain config instead");
+        }
+        Self::init(keys)
+    }
+
+    /// Switch the mode, which is set by the search chain config and can be changed by its reload.
+    pub fn set_mode(&self, mode: GoogleAPIMode) {
+        tracing::info!("The mode of Google Maps API is {mode:?}");
+        *self.mode.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = mode;
     }
 
     #[tracing::instrument(skip(self))]
     async fn find(&self, address: &str, params: SearchParams<'_>) -> LocResult {
-        let mut results = self.find_geo(address, params).await?;
+        let mut results = match self.find_geo(address, params).await {
+            // Text Search is billed separately, so it may still be available
+            Err(err) if is_budget_exceeded(&err) || ErrorClass::of(&err) == Some(ErrorClass::QuotaExceeded) => {
+                tracing::warn!("{err}, falling back to Text Search");
+                Vec::default()
+            },
+            res => res?
+        };
         if results.is_empty() {
             results = self.find_text(address, params).await?;
         }
@@ -110,39 +222,62 @@ impl GoogleLocFinder {
             .map(|(p1, p2)| format!("&bounds={},{}%7C{},{}", p1.0, p1.1, p2.0, p2.1))
             .unwrap_or_default();
         let encoded_address = urlencoding::encode(address);
-        let url = format!("https://maps.googleapis.com/maps/api/geocode/json?key={}&address={}&language={}&region={}{bounds_part}",
-                          self.api_key, encoded_address, params.lang_code, params.lang_code);
-        let resp = self.client.get(url).send().await?;
-        self.inc_resp_counter(&resp);
-
-        let json = resp.json::<serde_json::Value>().await?;
-        tracing::info!("Response from Google Maps Geocoding API: {json}");
-
-        let results = iter_over_array(&json["results"])
-            .filter_map(map_resp_geo)
-            .collect();
-        Ok(results)
+        let url = &format!("https://maps.googleapis.com/maps/api/geocode/json?address={}&language={}&region={}{bounds_part}",
+                          encoded_address, params.lang_code, params.lang_code);
+        self.geocode_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.geocode_budget.clone())
+                .with_extension(ApiKey::QueryParam("key", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Google, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Google Maps Geocoding API: {}", String::from_utf8_lossy(&body));
+            parse_geocode_response("geocode", &body)
+        }).await
     }
 
     #[tracing::instrument(skip(self))]
     async fn find_text(&self, address: &str, params: SearchParams<'_>) -> LocResult {
         self.text_req_counter.inc();
-        let resp = self.client.post("https://places.googleapis.com/v1/places:searchText")
-            .header(http::header::CONTENT_TYPE.as_str(), mime::APPLICATION_JSON.as_ref())
-            .header("X-Goog-Api-Key", &self.api_key)
-            .header("X-Goog-FieldMask", "places.displayName,places.formattedAddress,places.location")
-            .json(&SearchQuery::new(address, params.lang_code, params.location))
-            .send().await?;
-        self.inc_resp_counter(&resp);
-
-        let json = resp.json::<serde_json::Value>().await?;
-        tracing::info!("Response from Google Maps Text Search API: {json}");
-
-        let results: Vec<Location> = iter_over_array(&json["places"])
-            .filter_map(map_resp_place)
-            .collect();
+        self.text_keys.with_key(|key| async move {
+            let resp = self.client.post("https://places.googleapis.com/v1/places:searchText")
+                .header(http::header::CONTENT_TYPE.as_str(), mime::APPLICATION_JSON.as_ref())
+                .with_extension(SafeToRetry)
+                .with_extension(self.text_budget.clone())
+                .with_extension(ApiKey::Header("X-Goog-Api-Key", key))
+                .header("X-Goog-FieldMask", "places.id,places.displayName,places.formattedAddress,places.location,places.types,places.viewport,places.addressComponents")
+                .json(&SearchQuery::new(address, params.lang_code, params.location))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Google, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Google Maps Text Search API: {}", String::from_utf8_lossy(&body));
+            parse_text_search_response(&body)
+        }).await
+    }
 
-        Ok(results)
+    #[tracing::instrument(skip(self))]
+    async fn find_suggestions(&self, input: &str, params: SearchParams<'_>) -> LocResult {
+        self.autocomplete_req_counter.inc();
+        let query = &AutocompleteQuery::new(input, params.lang_code, params.location, session_token());
+        self.autocomplete_keys.with_key(|key| async move {
+            let resp = self.client.post("https://places.googleapis.com/v1/places:autocomplete")
+                .header(http::header::CONTENT_TYPE.as_str(), mime::APPLICATION_JSON.as_ref())
+                .with_extension(SafeToRetry)
+                .with_extension(self.autocomplete_budget.clone())
+                .with_extension(ApiKey::Header("X-Goog-Api-Key", key))
+                .json(query)
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Google, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Google Maps Autocomplete API: {}", String::from_utf8_lossy(&body));
+            parse_autocomplete_response(&body)
+        }).await
     }
 }
 
@@ -151,13 +286,89 @@ impl LocFinder for GoogleLocFinder {
     #[tracing::instrument(skip(self))]
     async fn find(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> LocResult {
         let params = SearchParams { lang_code, location };
-        match *GAPI_MODE {
+        let mode = *self.mode.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match mode {
             GoogleAPIMode::Text => self.find_text(query, params).await,
             GoogleAPIMode::GeoText => self.find(query, params).await,
+            GoogleAPIMode::Autocomplete => self.find_suggestions(query, params).await,
         }
     }
 }
 
+#[async_trait]
+impl SuggestionResolver for GoogleLocFinder {
+    /// Request the details of the place within the session of the autocomplete requests, which ends the session.
+    #[tracing::instrument(skip(self))]
+    async fn resolve(&self, suggestion: &Suggestion, lang_code: &str) -> LocResult {
+        self.details_req_counter.inc();
+        let session_part = session_token()
+            .map(|token| format!("&sessionToken={}", urlencoding::encode(&token)))
+            .unwrap_or_default();
+        let url = &format!("https://places.googleapis.com/v1/places/{}?languageCode={lang_code}{session_part}",
+                          urlencoding::encode(&suggestion.place_id));
+        self.details_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.details_budget.clone())
+                .with_extension(ApiKey::Header("X-Goog-Api-Key", key))
+                .header("X-Goog-FieldMask", "id,displayName,formattedAddress,location,types,viewport,addressComponents")
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Google, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Google Maps Place Details API: {}", String::from_utf8_lossy(&body));
+            parse_place_details_response(&body)
+        }).await
+    }
+}
+
+#[async_trait]
+impl NearbyLocFinder for GoogleLocFinder {
+    /// The places are searched by their types, so the query itself isn't needed.
+    #[tracing::instrument(skip(self))]
+    async fn find_nearby(&self, category: Category, _query: &str, lang_code: &str, location: (f64, f64)) -> LocResult {
+        self.nearby_req_counter.inc();
+        let query = &NearbyQuery::new(category, lang_code, location, nearby::radius());
+        self.nearby_keys.with_key(|key| async move {
+            let resp = self.client.post("https://places.googleapis.com/v1/places:searchNearby")
+                .header(http::header::CONTENT_TYPE.as_str(), mime::APPLICATION_JSON.as_ref())
+                .with_extension(SafeToRetry)
+                .with_extension(self.nearby_budget.clone())
+                .with_extension(ApiKey::Header("X-Goog-Api-Key", key))
+                .header("X-Goog-FieldMask", "places.id,places.displayName,places.formattedAddress,places.location,places.types,places.viewport,places.addressComponents")
+                .json(query)
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Google, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Google Maps Nearby Search API: {}", String::from_utf8_lossy(&body));
+            parse_text_search_response(&body)
+        }).await
+    }
+}
+
+#[async_trait]
+impl ReverseLocFinder for GoogleLocFinder {
+    #[tracing::instrument(skip(self))]
+    async fn find_by_coords(&self, latitude: f64, longitude: f64, lang_code: &str) -> LocResult {
+        self.reverse_geocode_req_counter.inc();
+        let url = &format!("https://maps.googleapis.com/maps/api/geocode/json?latlng={latitude},{longitude}&language={lang_code}");
+        self.reverse_geocode_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.reverse_geocode_budget.clone())
+                .with_extension(ApiKey::QueryParam("key", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Google, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Google Maps Reverse Geocoding API: {}", String::from_utf8_lossy(&body));
+            parse_geocode_response("reverse-geocode", &body)
+        }).await
+    }
+}
+
 impl WithCachedResponseCounters for GoogleLocFinder {
     fn cached_resp_counter(&self) -> &prometheus::Counter {
         &self.cached_resp_counter
@@ -168,42 +379,284 @@ impl WithCachedResponseCounters for GoogleLocFinder {
     }
 }
 
-type IterOverJsonArray<'a> = core::iter::FlatMap<
-    core::option::IntoIter<&'a Vec<serde_json::Value>>,
-    core::slice::Iter<'a, serde_json::Value>,
-    fn(&Vec<serde_json::value::Value>) -> core::slice::Iter<serde_json::Value>
->;
+#[derive(Deserialize)]
+struct GeocodeResponse {
+    status: String,
+    error_message: Option<String>,
+    #[serde(default)]
+    results: Vec<GeocodeResult>,
+}
+
+#[derive(Deserialize)]
+struct GeocodeResult {
+    formatted_address: String,
+    geometry: Geometry,
+    place_id: Option<String>,
+    #[serde(default)]
+    types: Vec<String>,
+    #[serde(default)]
+    address_components: Vec<GeocodeAddressComponent>,
+}
+
+#[derive(Deserialize)]
+struct Geometry {
+    location: LatLng,
+    location_type: Option<String>,
+    viewport: Option<Viewport>,
+}
+
+#[derive(Deserialize)]
+struct LatLng {
+    lat: f64,
+    lng: f64,
+}
+
+#[derive(Deserialize)]
+struct Viewport {
+    southwest: LatLng,
+    northeast: LatLng,
+}
+
+#[derive(Deserialize)]
+struct GeocodeAddressComponent {
+    long_name: String,
+    #[serde(default)]
+    types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TextSearchResponse {
+    #[serde(default)]
+    places: Vec<Place>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Place {
+    id: Option<String>,
+    display_name: LocalizedText,
+    formatted_address: Option<String>,
+    location: PlaceLatLng,
+    #[serde(default)]
+    types: Vec<String>,
+    viewport: Option<PlaceViewport>,
+    #[serde(default)]
+    address_components: Vec<PlaceAddressComponent>,
+}
 
-fn iter_over_array(v: &'_ serde_json::Value) -> IterOverJsonArray<'_> {
-    v.as_array().into_iter()
-        .flat_map(|x| x.iter())
+#[derive(Deserialize)]
+struct AutocompleteResponse {
+    #[serde(default)]
+    suggestions: Vec<AutocompleteSuggestion>,
 }
 
-fn map_resp_geo(v: &serde_json::Value) -> Option<Location> {
-    let address = Some(v["formatted_address"].as_str()?.to_string());
+/// Query predictions are not requested, so only place predictions are expected.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AutocompleteSuggestion {
+    place_prediction: Option<PlacePrediction>,
+}
 
-    let loc = &v["geometry"]["location"];
-    let latitude: f64 = loc["lat"].as_f64()?;
-    let longitude: f64 = loc["lng"].as_f64()?;
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlacePrediction {
+    place_id: String,
+    text: LocalizedText,
+    #[serde(default)]
+    types: Vec<String>,
+    distance_meters: Option<f64>,
+}
 
-    Some(Location {
-        address, latitude, longitude
-    })
+#[derive(Deserialize)]
+struct LocalizedText {
+    text: String,
 }
 
-fn map_resp_place(v: &serde_json::Value) -> Option<Location> {
-    let name = v["displayName"]["text"].as_str()?.to_string();
-    let address = v["formattedAddress"].as_str()?.to_string();
-    let full_address = Some(format!("{name}, {address}"));
+#[derive(Deserialize)]
+struct PlaceLatLng {
+    latitude: f64,
+    longitude: f64,
+}
 
-    let loc = &v["location"];
-    let latitude: f64 = loc["latitude"].as_f64()?;
-    let longitude: f64 = loc["longitude"].as_f64()?;
+#[derive(Deserialize)]
+struct PlaceViewport {
+    low: PlaceLatLng,
+    high: PlaceLatLng,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaceAddressComponent {
+    long_text: String,
+    #[serde(default)]
+    types: Vec<String>,
+}
 
-    Some(Location {
-        address: full_address,
-        latitude, longitude
-    })
+/// Used for both the direct and the reverse Geocoding API, which report errors in the `status` field of responses with the 200 code.
+pub(super) fn parse_geocode_response(api: &'static str, body: &[u8]) -> LocResult {
+    let resp: GeocodeResponse = response::parse(Provider::Google, api, body)?;
+    if let Some(class) = ErrorClass::from_google_status(&resp.status) {
+        let message = resp.error_message.unwrap_or(resp.status);
+        return Err(ProviderError::new(Provider::Google, class, message).into())
+    }
+    let results = resp.results.into_iter()
+        .map(map_resp_geo)
+        .collect();
+    Ok(results)
+}
+
+pub(super) fn parse_text_search_response(body: &[u8]) -> LocResult {
+    let resp: TextSearchResponse = response::parse(Provider::Google, "place-text", body)?;
+    let results = resp.places.into_iter()
+        .map(map_resp_place)
+        .collect();
+    Ok(results)
+}
+
+pub(super) fn parse_autocomplete_response(body: &[u8]) -> LocResult {
+    let resp: AutocompleteResponse = response::parse(Provider::Google, "place-autocomplete", body)?;
+    let results = resp.suggestions.into_iter()
+        .filter_map(|suggestion| suggestion.place_prediction)
+        .map(map_prediction)
+        .collect();
+    Ok(results)
+}
+
+pub(super) fn parse_place_details_response(body: &[u8]) -> LocResult {
+    let place: Place = response::parse(Provider::Google, "place-details", body)?;
+    Ok(vec![map_resp_place(place)])
+}
+
+fn map_resp_geo(result: GeocodeResult) -> Location {
+    let geometry = result.geometry;
+    let components = result.address_components.iter()
+        .map(|c| (c.long_name.as_str(), c.types.as_slice()));
+    Location {
+        address: Some(result.formatted_address),
+        provider: Some(Provider::Google),
+        place_id: result.place_id,
+        kind: map_types(&result.types),
+        confidence: geometry.location_type.as_deref().and_then(map_location_type),
+        bbox: geometry.viewport.map(|viewport| BoundingBox {
+            south: viewport.southwest.lat,
+            west: viewport.southwest.lng,
+            north: viewport.northeast.lat,
+            east: viewport.northeast.lng,
+        }),
+        components: map_address_components(components),
+        ..Location::new(geometry.location.lat, geometry.location.lng)
+    }
+}
+
+fn map_resp_place(place: Place) -> Location {
+    let name = place.display_name.text;
+    let full_address = match place.formatted_address {
+        Some(address) => format!("{name}, {address}"),
+        None => name.clone(),
+    };
+    let kind = match map_types(&place.types) {
+        PlaceKind::Other => PlaceKind::Poi,
+        kind => kind
+    };
+    let components = place.address_components.iter()
+        .map(|c| (c.long_text.as_str(), c.types.as_slice()));
+    Location {
+        address: Some(full_address),
+        provider: Some(Provider::Google),
+        place_id: place.id,
+        kind,
+        bbox: place.viewport.map(|viewport| BoundingBox {
+            south: viewport.low.latitude,
+            west: viewport.low.longitude,
+            north: viewport.high.latitude,
+            east: viewport.high.longitude,
+        }),
+        components: AddressComponents {
+            name: Some(name),
+            ..map_address_components(components)
+        },
+        ..Location::new(place.location.latitude, place.location.longitude)
+    }
+}
+
+fn map_prediction(prediction: PlacePrediction) -> Location {
+    Location {
+        address: Some(prediction.text.text),
+        distance: prediction.distance_meters,
+        provider: Some(Provider::Google),
+        place_id: Some(prediction.place_id),
+        kind: map_types(&prediction.types),
+        suggestion: true,
+        ..Location::new(0.0, 0.0)
+    }
+}
+
+/// Types of Table A of Places API, the ones Nearby Search accepts.
+fn map_category(category: Category) -> &'static [&'static str] {
+    match category {
+        Category::Pharmacy => &["pharmacy", "drugstore"],
+        Category::Cafe => &["cafe", "coffee_shop"],
+        Category::Restaurant => &["restaurant"],
+        Category::Bar => &["bar", "pub"],
+        Category::Atm => &["atm"],
+        Category::Bank => &["bank"],
+        Category::FuelStation => &["gas_station"],
+        Category::Supermarket => &["supermarket", "grocery_store"],
+        Category::Hospital => &["hospital"],
+        Category::Hotel => &["hotel", "hostel"],
+        Category::Parking => &["parking"],
+    }
+}
+
+/// The types are ordered from the most specific one in the responses.
+fn map_types(types: &[String]) -> PlaceKind {
+    types.iter()
+        .map(|t| match t.as_str() {
+            "street_address" | "premise" | "subpremise" => PlaceKind::House,
+            "route" | "intersection" => PlaceKind::Street,
+            "locality" | "postal_town" | "sublocality" => PlaceKind::City,
+            "administrative_area_level_1" | "administrative_area_level_2" => PlaceKind::Region,
+            "country" => PlaceKind::Country,
+            "establishment" | "point_of_interest" => PlaceKind::Poi,
+            _ => PlaceKind::Other
+        })
+        .find(|kind| *kind != PlaceKind::Other)
+        .unwrap_or_default()
+}
+
+fn map_location_type(location_type: &str) -> Option<f64> {
+    match location_type {
+        "ROOFTOP" => Some(1.0),
+        "RANGE_INTERPOLATED" => Some(0.8),
+        "GEOMETRIC_CENTER" => Some(0.6),
+        "APPROXIMATE" => Some(0.4),
+        _ => None
+    }
+}
+
+/// Both the Geocoding API (`long_name`) and the Places API (`longText`) return an array of components marked with types.
+fn map_address_components<'a>(components: impl Iterator<Item = (&'a str, &'a [String])>) -> AddressComponents {
+    let mut result = AddressComponents::default();
+    for (text, types) in components {
+        let has_type = |t: &str| types.iter().any(|x| x == t);
+        let field = if has_type("country") {
+            &mut result.country
+        } else if has_type("administrative_area_level_1") {
+            &mut result.region
+        } else if has_type("locality") || has_type("postal_town") {
+            &mut result.city
+        } else if has_type("route") {
+            &mut result.street
+        } else if has_type("street_number") {
+            &mut result.house
+        } else if has_type("postal_code") {
+            &mut result.postcode
+        } else {
+            continue
+        };
+        field.get_or_insert_with(|| text.to_string());
+    }
+    result
 }
 
 fn get_bounds(center: (f64, f64), radius: f64) -> ((f64, f64), (f64, f64)) {
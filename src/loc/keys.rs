@@ -0,0 +1,163 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use once_cell::sync::Lazy;
+use prometheus::Opts;
+use super::cache::{get_env_or_default, is_stale_lookup};
+use super::errors::{ErrorClass, ProviderError};
+use super::{LocResult, Provider};
+use crate::metrics;
+
+const ENV_API_KEY_COOLDOWN_SECS: &str = "API_KEY_COOLDOWN_SECS";
+const DEFAULT_API_KEY_COOLDOWN_SECS: u64 = 600;
+
+static COOLDOWN: Lazy<Duration> = Lazy::new(|| {
+    let val = get_env_or_default(ENV_API_KEY_COOLDOWN_SECS, DEFAULT_API_KEY_COOLDOWN_SECS);
+    tracing::info!("{ENV_API_KEY_COOLDOWN_SECS} is {val}");
+    Duration::from_secs(val)
+});
+
+static USAGE_COUNTER: Lazy<prometheus::CounterVec> = Lazy::new(|| {
+    let opts = Opts::new("api_key_requests_total", "count of requests made with each API key");
+    metrics::REGISTRY.register_counter_vec("API key requests", opts, &["provider", "key"])
+});
+static SIDELINED_COUNTER: Lazy<prometheus::CounterVec> = Lazy::new(|| {
+    let opts = Opts::new("api_key_sidelined_total", "count of times an API key was sidelined after a quota or auth error");
+    metrics::REGISTRY.register_counter_vec("sidelined API keys", opts, &["provider", "key"])
+});
+
+struct PooledKey {
+    value: String,
+    weight: usize,
+    /// Used instead of the key in metrics and logs.
+    id: String,
+    sidelined_until: Mutex<Option<Instant>>,
+}
+
+impl PooledKey {
+    fn is_available(&self, now: Instant) -> bool {
+        self.lock().is_none_or(|until| now >= until)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.sidelined_until.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Keys of a provider picked by weighted round-robin. A key that got a quota or auth error is sidelined for a cooldown,
+/// and the next one is used meanwhile.
+///
+/// Configured by a comma-separated list of keys, each optionally followed by its weight: `key1:3,key2`.
+pub struct KeyPool {
+    provider: Provider,
+    keys: Vec<PooledKey>,
+    cooldown: Duration,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Returns `None` if the variable is not set or contains no keys.
+    pub fn from_env(provider: Provider, env: &str) -> Option<KeyPool> {
+        let value = std::env::var(env).ok()?;
+        let pool = Self::with_cooldown(provider, &value, *COOLDOWN);
+        tracing::info!("{env} contains {} key(s)", pool.keys.len());
+        Some(pool).filter(|pool| !pool.keys.is_empty())
+    }
+
+    pub fn with_cooldown(provider: Provider, keys: &str, cooldown: Duration) -> KeyPool {
+        let keys = keys.split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let (value, weight) = match key.rsplit_once(':') {
+                    Some((value, weight)) => (value, weight.parse().unwrap_or_else(|_| {
+                        tracing::error!("invalid weight of the {} key: {weight}", mask(value));
+                        1
+                    })),
+                    None => (key, 1),
+                };
+                PooledKey {
+                    value: value.to_owned(),
+                    weight,
+                    id: mask(value),
+                    sidelined_until: Mutex::default(),
+                }
+            })
+            .filter(|key| key.weight > 0)
+            .collect();
+        KeyPool { provider, keys, cooldown, next: AtomicUsize::new(0) }
+    }
+
+    /// The same keys for another API of the provider. Quotas are counted per API, so a key rejected by one API may
+    /// still be accepted by the others and is sidelined only in the pool of the former.
+    pub fn for_another_api(&self) -> KeyPool {
+        let keys = self.keys.iter()
+            .map(|key| PooledKey {
+                value: key.value.clone(),
+                weight: key.weight,
+                id: key.id.clone(),
+                sidelined_until: Mutex::default(),
+            })
+            .collect();
+        KeyPool { provider: self.provider, keys, cooldown: self.cooldown, next: AtomicUsize::new(0) }
+    }
+
+    /// Make the call with the next available key. If the provider rejects the key, it's sidelined and the call is
+    /// repeated with the next one, so the quota error is returned only when all keys of the pool are sidelined.
+    pub async fn with_key<F, Fut>(&self, call: F) -> LocResult
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = LocResult>,
+    {
+        // the response is taken from the cache, so no key is needed, even if all of them are sidelined
+        if is_stale_lookup() {
+            return call(String::default()).await
+        }
+        loop {
+            let key = self.select(Instant::now())
+                .ok_or_else(|| ProviderError::new(self.provider, ErrorClass::QuotaExceeded, "all API keys are sidelined"))?;
+            USAGE_COUNTER.with_label_values(&[self.provider.as_ref(), &key.id]).inc();
+
+            let result = call(key.value.clone()).await;
+            match &result {
+                Err(err) if matches!(ErrorClass::of(err), Some(ErrorClass::QuotaExceeded | ErrorClass::AuthFailure)) => {
+                    tracing::warn!("the {} key of {:?} is rejected: {err}", key.id, self.provider);
+                    self.sideline(key, Instant::now());
+                },
+                _ => return result
+            }
+        }
+    }
+
+    /// The key at the next position of the weighted round, or the closest available one after it.
+    fn select(&self, now: Instant) -> Option<&PooledKey> {
+        let total_weight: usize = self.keys.iter().map(|key| key.weight).sum();
+        if total_weight == 0 {
+            return None
+        }
+        let mut position = self.next.fetch_add(1, Ordering::Relaxed) % total_weight;
+        let first = self.keys.iter()
+            .position(|key| {
+                let found = position < key.weight;
+                position = position.saturating_sub(key.weight);
+                found
+            })
+            .unwrap_or_default();
+        (0..self.keys.len())
+            .map(|i| &self.keys[(first + i) % self.keys.len()])
+            .find(|key| key.is_available(now))
+    }
+
+    fn sideline(&self, key: &PooledKey, now: Instant) {
+        tracing::warn!("the {} key of {:?} is sidelined for {:?}", key.id, self.provider, self.cooldown);
+        SIDELINED_COUNTER.with_label_values(&[self.provider.as_ref(), &key.id]).inc();
+        *key.lock() = Some(now + self.cooldown);
+    }
+}
+
+/// A short hash identifying the key without revealing it.
+pub(super) fn mask(key: &str) -> String {
+    let hash = sha256::digest(key);
+    format!("sha256:{}", &hash[..8])
+}
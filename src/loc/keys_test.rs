@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+use std::time::Duration;
+use anyhow::anyhow;
+use super::errors::{ErrorClass, ProviderError};
+use super::keys::{mask, KeyPool};
+use super::{LocResult, Provider};
+
+const COOLDOWN: Duration = Duration::from_millis(50);
+
+#[tokio::test]
+async fn test_weighted_round_robin() {
+    let pool = KeyPool::with_cooldown(Provider::Google, "a:2, b,, c:0, d:x", COOLDOWN);
+    let mut used = Vec::new();
+    for _ in 0..8 {
+        used.extend(call(&pool, |_| Ok(Vec::default())).await.0);
+    }
+    // the zero-weighted key is dropped, an invalid weight is replaced by 1
+    assert_eq!(used, ["a", "a", "b", "d", "a", "a", "b", "d"]);
+}
+
+#[tokio::test]
+async fn test_rejected_key_is_replaced() {
+    let pool = KeyPool::with_cooldown(Provider::Yandex, "a,b", COOLDOWN);
+    let (used, result) = call(&pool, |key| match key {
+        "a" => Err(ProviderError::new(Provider::Yandex, ErrorClass::QuotaExceeded, "quota").into()),
+        _ => Ok(Vec::default()),
+    }).await;
+    assert_eq!(used, ["a", "b"]);
+    assert!(result.is_ok(), "the call must be repeated with the next key");
+
+    for _ in 0..3 {
+        assert_eq!(call(&pool, |_| Ok(Vec::default())).await.0, ["b"]);
+    }
+
+    tokio::time::sleep(COOLDOWN).await;
+    let mut used = call(&pool, |_| Ok(Vec::default())).await.0;
+    used.extend(call(&pool, |_| Ok(Vec::default())).await.0);
+    assert!(used.contains(&"a".to_owned()), "the key must be back after the cooldown");
+}
+
+#[tokio::test]
+async fn test_key_is_not_sidelined_by_other_errors() {
+    let pool = KeyPool::with_cooldown(Provider::Google, "a,b", COOLDOWN);
+    let (used, _) = call(&pool, |_| Err(anyhow!("connection reset"))).await;
+    assert_eq!(used, ["a"], "the call must not be repeated");
+    call(&pool, |_| Err(ProviderError::new(Provider::Google, ErrorClass::InvalidRequest, "bad query").into())).await;
+    assert_eq!(call(&pool, |_| Ok(Vec::default())).await.0, ["a"]);
+    assert_eq!(call(&pool, |_| Ok(Vec::default())).await.0, ["b"]);
+}
+
+#[tokio::test]
+async fn test_all_keys_sidelined() {
+    let pool = KeyPool::with_cooldown(Provider::Google, "a,b", COOLDOWN);
+    let (used, result) = call(&pool, |_| Err(ProviderError::new(Provider::Google, ErrorClass::AuthFailure, "denied").into())).await;
+    assert_eq!(used, ["a", "b"]);
+    assert_eq!(ErrorClass::of(&result.unwrap_err()), Some(ErrorClass::QuotaExceeded));
+
+    let (used, result) = call(&pool, |_| Ok(Vec::default())).await;
+    assert!(used.is_empty());
+    assert_eq!(ErrorClass::of(&result.unwrap_err()), Some(ErrorClass::QuotaExceeded));
+}
+
+#[tokio::test]
+async fn test_keys_are_sidelined_per_api() {
+    let geocode = KeyPool::with_cooldown(Provider::Google, "a", COOLDOWN);
+    let text = geocode.for_another_api();
+    let (_, result) = call(&geocode, |_| Err(ProviderError::new(Provider::Google, ErrorClass::QuotaExceeded, "quota").into())).await;
+    assert_eq!(ErrorClass::of(&result.unwrap_err()), Some(ErrorClass::QuotaExceeded));
+
+    let (used, result) = call(&text, |_| Ok(Vec::default())).await;
+    assert_eq!(used, ["a"], "the key must stay usable for other APIs");
+    assert!(result.is_ok());
+    assert!(call(&geocode, |_| Ok(Vec::default())).await.0.is_empty());
+}
+
+#[test]
+fn test_mask() {
+    let key = "AIzaSyD-secret-key";
+    let masked = mask(key);
+    assert!(!masked.contains("secret"));
+    assert_eq!(masked, mask(key));
+    assert_ne!(masked, mask("another-key"));
+}
+
+/// Returns the keys used for the call in order and its result. The response for each key is made by `respond`.
+async fn call(pool: &KeyPool, respond: impl Fn(&str) -> LocResult) -> (Vec<String>, LocResult) {
+    let used = RefCell::new(Vec::new());
+    let result = pool.with_key(|key| {
+        let result = respond(&key);
+        used.borrow_mut().push(key);
+        async { result }
+    }).await;
+    (used.into_inner(), result)
+}
@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+use once_cell::sync::Lazy;
+use super::{geo, Location};
+
+const ENV_DEDUP_DISTANCE_METERS: &str = "DEDUP_DISTANCE_METERS";
+const DEFAULT_DEDUP_DISTANCE_METERS: f64 = 25.0;
+
+/// Minimal share of common words for two addresses to be considered the same.
+const ADDRESS_SIMILARITY_THRESHOLD: f64 = 0.5;
+
+static DEDUP_DISTANCE: Lazy<f64> = Lazy::new(|| {
+    let val = std::env::var(ENV_DEDUP_DISTANCE_METERS)
+        .ok()
+        .and_then(|v| v.parse().map_err(|e| tracing::error!("couldn't parse {ENV_DEDUP_DISTANCE_METERS}: {e}")).ok())
+        .unwrap_or(DEFAULT_DEDUP_DISTANCE_METERS);
+    tracing::info!("{ENV_DEDUP_DISTANCE_METERS} is {val}");
+    val
+});
+
+/// Collapse near-identical points returned by different providers into one entry.
+/// The order is preserved, so the entry coming from the finder with a higher priority wins,
+/// but it's complemented with the data of its duplicates.
+pub fn dedup(locations: Vec<Location>) -> Vec<Location> {
+    dedup_within(locations, *DEDUP_DISTANCE)
+}
+
+pub(super) fn dedup_within(locations: Vec<Location>, max_distance: f64) -> Vec<Location> {
+    let mut merged: Vec<Location> = Vec::with_capacity(locations.len());
+    for loc in locations {
+        match merged.iter_mut().find(|m| is_duplicate(m, &loc, max_distance)) {
+            Some(existing) => existing.absorb(loc),
+            None => merged.push(loc),
+        }
+    }
+    merged
+}
+
+fn is_duplicate(a: &Location, b: &Location, max_distance: f64) -> bool {
+    if a.is_same_place(b) {
+        return true
+    }
+    // the coordinates of suggestions are unknown
+    if a.suggestion || b.suggestion {
+        return false
+    }
+    let dist = geo::distance((a.latitude, a.longitude), (b.latitude, b.longitude));
+    dist <= max_distance && addresses_similar(a.address.as_deref(), b.address.as_deref())
+}
+
+fn addresses_similar(a: Option<&str>, b: Option<&str>) -> bool {
+    let (Some(a), Some(b)) = (a, b) else {
+        return true
+    };
+    let (a, b) = (tokenize(a), tokenize(b));
+    if a.is_empty() || b.is_empty() {
+        return true
+    }
+    let common = a.intersection(&b).count() as f64;
+    let smaller = a.len().min(b.len()) as f64;
+    common / smaller >= ADDRESS_SIMILARITY_THRESHOLD
+}
+
+fn tokenize(address: &str) -> HashSet<String> {
+    address.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,94 @@
+use super::{AddressComponents, Location, Provider};
+use super::merge::dedup_within;
+
+#[test]
+fn test_dedup_collapses_near_identical_points() {
+    let locations = vec![
+        location(Some("Red Square, Moscow, Russia"), 55.753930, 37.620795),
+        location(Some("Red Square, Moscow"), 55.753960, 37.620800),
+        location(Some("Lenin's Mausoleum, Red Square, Moscow"), 55.753700, 37.619800),
+    ];
+
+    let merged = dedup_within(locations, 25.0);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].address.as_deref(), Some("Red Square, Moscow, Russia"));
+}
+
+#[test]
+fn test_dedup_keeps_different_places_at_the_same_point() {
+    let locations = vec![
+        location(Some("Coffee House, Tverskaya 1"), 55.757, 37.615),
+        location(Some("Pharmacy 36.6"), 55.757, 37.615),
+    ];
+
+    let merged = dedup_within(locations, 25.0);
+    assert_eq!(merged.len(), 2);
+}
+
+#[test]
+fn test_dedup_fills_missing_address() {
+    let locations = vec![
+        location(None, 55.757, 37.615),
+        location(Some("Tverskaya 1"), 55.757, 37.615),
+    ];
+
+    let merged = dedup_within(locations, 25.0);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].address.as_deref(), Some("Tverskaya 1"));
+}
+
+#[test]
+fn test_dedup_collapses_same_place_id() {
+    let locations = vec![
+        Location {
+            place_id: Some("way/123".to_string()),
+            provider: Some(Provider::OpenStreetMap),
+            ..location(Some("Gorky Park"), 55.7298, 37.6011)
+        },
+        Location {
+            place_id: Some("way/123".to_string()),
+            provider: Some(Provider::OpenStreetMap),
+            ..location(Some("Central Park of Culture and Leisure"), 55.7312, 37.6035)
+        },
+    ];
+
+    let merged = dedup_within(locations, 25.0);
+    assert_eq!(merged.len(), 1);
+}
+
+#[test]
+fn test_dedup_keeps_more_confident_coordinates_and_fills_components() {
+    let locations = vec![
+        Location {
+            confidence: Some(0.4),
+            components: AddressComponents {
+                city: Some("Moscow".to_string()),
+                ..AddressComponents::default()
+            },
+            ..location(Some("Tverskaya 1"), 55.75700, 37.61500)
+        },
+        Location {
+            confidence: Some(1.0),
+            components: AddressComponents {
+                street: Some("Tverskaya".to_string()),
+                house: Some("1".to_string()),
+                ..AddressComponents::default()
+            },
+            ..location(Some("Tverskaya 1"), 55.75705, 37.61505)
+        },
+    ];
+
+    let merged = dedup_within(locations, 25.0);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].latitude(), 55.75705);
+    assert_eq!(merged[0].confidence, Some(1.0));
+    assert_eq!(merged[0].components.city.as_deref(), Some("Moscow"));
+    assert_eq!(merged[0].components.street.as_deref(), Some("Tverskaya"));
+}
+
+fn location(address: Option<&str>, latitude: f64, longitude: f64) -> Location {
+    Location {
+        address: address.map(str::to_string),
+        ..Location::new(latitude, longitude)
+    }
+}
@@ -1,20 +1,93 @@
-use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use async_trait::async_trait;
+use futures::future::{join_all, BoxFuture};
 use once_cell::sync::Lazy;
+use prometheus::Opts;
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumString;
+use breaker::{CallGuard, CircuitBreaker, CircuitOpen};
+use budget::is_budget_exceeded;
+use errors::ErrorClass;
+use nearby::{Category, NearbyLocFinderChainWrapper};
+use reverse::{DynReverseLocFinder, ReverseLocFinder, ReverseLocFinderChainWrapper};
+use result_cache::ResultCache;
+use routing::Routes;
+use suggest::Suggestion;
+use throttle::is_queue_full;
+use crate::metrics;
 
 pub mod google;
 pub mod yandex;
 pub mod osm;
+pub mod photon;
+pub mod overpass;
+pub mod gazetteer;
 pub mod cache;
+pub mod geo;
+mod merge;
+mod ranking;
+pub mod reverse;
+pub mod nearby;
+mod breaker;
+pub mod errors;
+pub mod credentials;
+pub mod keys;
+pub mod suggest;
+pub mod retry;
+pub mod throttle;
+mod response;
+pub mod budget;
+pub mod formatter;
+pub mod config;
+pub mod providers;
+pub mod routing;
+pub mod result_cache;
 
 #[cfg(test)]
 mod test;
 #[cfg(test)]
 mod cache_test;
+#[cfg(test)]
+mod merge_test;
+#[cfg(test)]
+mod ranking_test;
+#[cfg(test)]
+mod breaker_test;
+#[cfg(test)]
+mod budget_test;
+#[cfg(test)]
+mod photon_test;
+#[cfg(test)]
+mod gazetteer_test;
+#[cfg(test)]
+mod formatter_test;
+#[cfg(test)]
+mod config_test;
+#[cfg(test)]
+mod routing_test;
+#[cfg(test)]
+mod result_cache_test;
+#[cfg(test)]
+mod errors_test;
+#[cfg(test)]
+mod credentials_test;
+#[cfg(test)]
+mod keys_test;
+#[cfg(test)]
+mod throttle_test;
+#[cfg(test)]
+mod retry_test;
 
 const DISABLE_ENV_PREFIX: &str = "DISABLE_FINDER_";
 
+const ENV_SEARCH_CHAIN_MODE: &str = "SEARCH_CHAIN_MODE";
+const ENV_SEARCH_CHAIN_DEADLINE_MS: &str = "SEARCH_CHAIN_DEADLINE_MS";
+const DEFAULT_SEARCH_CHAIN_DEADLINE_MS: u64 = 5000;
+const ENV_STALE_REFRESH_DELAY_SECS: &str = "STALE_REFRESH_DELAY_SECS";
+const DEFAULT_STALE_REFRESH_DELAY_SECS: u64 = 5;
+
 static SEARCH_RADIUS: Lazy<f64> = Lazy::new(|| {
     let val: u32 = std::env::var("SEARCH_RADIUS_METERS")
         .ok()
@@ -24,16 +97,137 @@ static SEARCH_RADIUS: Lazy<f64> = Lazy::new(|| {
     f64::from(val) / 10_000.0   // 6 digits after a comma have accuracy in 0.1 m, so we need to shift the dot at 5 digits
 });
 
-#[derive(Debug, Clone)]
+static STALE_RESULTS_COUNTER: Lazy<prometheus::Counter> = Lazy::new(|| {
+    let opts = Opts::new("loc_stale_results_total", "count of stale results served when no provider managed to answer");
+    metrics::REGISTRY.register_counter("stale results", opts)
+});
+
+static SEARCH_CHAIN_MODE: Lazy<SearchChainMode> = Lazy::new(|| {
+    let val = std::env::var(ENV_SEARCH_CHAIN_MODE)
+        .ok()
+        .and_then(|v| SearchChainMode::from_str(&v)
+            .inspect_err(|e| tracing::error!("couldn't parse {ENV_SEARCH_CHAIN_MODE}: {e}"))
+            .ok())
+        .unwrap_or_default();
+    tracing::info!("{ENV_SEARCH_CHAIN_MODE} is {val:?}");
+    val
+});
+
+static SEARCH_CHAIN_DEADLINE: Lazy<Duration> = Lazy::new(|| {
+    let val: u64 = std::env::var(ENV_SEARCH_CHAIN_DEADLINE_MS)
+        .ok()
+        .and_then(|v| v.parse().map_err(|e| tracing::error!("couldn't parse {ENV_SEARCH_CHAIN_DEADLINE_MS}: {e}")).ok())
+        .unwrap_or(DEFAULT_SEARCH_CHAIN_DEADLINE_MS);
+    tracing::info!("{ENV_SEARCH_CHAIN_DEADLINE_MS} is {val}");
+    Duration::from_millis(val)
+});
+
+static STALE_REFRESH_DELAY: Lazy<Duration> = Lazy::new(|| {
+    let val = cache::get_env_or_default(ENV_STALE_REFRESH_DELAY_SECS, DEFAULT_STALE_REFRESH_DELAY_SECS);
+    tracing::info!("{ENV_STALE_REFRESH_DELAY_SECS} is {val}");
+    Duration::from_secs(val)
+});
+
+#[derive(EnumString, Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[strum(ascii_case_insensitive)]
+pub enum SearchChainMode {
+    #[default]
+    Sequential, // finders are awaited one by one, the first non-empty result wins
+    Parallel,   // all finders are run concurrently under a shared deadline, their results are merged
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, strum_macros::AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Provider {
+    Google,
+    Yandex,
+    #[serde(rename = "osm")]
+    #[strum(serialize = "osm")]
+    OpenStreetMap,
+    Photon,
+    Gazetteer,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaceKind {
+    House,
+    Street,
+    Poi,
+    City,
+    Region,
+    Country,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+/// Parts of the address as returned by the provider. `name` is the name of a POI or an organization.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressComponents {
+    pub name: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub street: Option<String>,
+    pub house: Option<String>,
+    pub postcode: Option<String>,
+}
+
+impl AddressComponents {
+    /// Take the components missing here from another description of the same place.
+    fn fill_from(&mut self, other: AddressComponents) {
+        self.name = self.name.take().or(other.name);
+        self.country = self.country.take().or(other.country);
+        self.region = self.region.take().or(other.region);
+        self.city = self.city.take().or(other.city);
+        self.street = self.street.take().or(other.street);
+        self.house = self.house.take().or(other.house);
+        self.postcode = self.postcode.take().or(other.postcode);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Location {
     address: Option<String>,
     latitude: f64,
-    longitude: f64
+    longitude: f64,
+    distance: Option<f64>,
+
+    provider: Option<Provider>,
+    place_id: Option<String>,
+    kind: PlaceKind,
+    /// How sure the provider is about the match, from 0 to 1.
+    confidence: Option<f64>,
+    bbox: Option<BoundingBox>,
+    components: AddressComponents,
+    /// Only a suggestion of an autocomplete API; the coordinates are unknown until it's resolved.
+    #[serde(default)]
+    suggestion: bool,
 }
 
 impl Location {
     pub fn new(latitude: f64, longitude: f64) -> Location {
-        Location { address: None, latitude, longitude }
+        Location {
+            address: None,
+            latitude,
+            longitude,
+            distance: None,
+            provider: None,
+            place_id: None,
+            kind: PlaceKind::default(),
+            confidence: None,
+            bbox: None,
+            components: AddressComponents::default(),
+            suggestion: false,
+        }
     }
 
     pub fn address(&self) -> Option<String> {
@@ -47,6 +241,60 @@ impl Location {
     pub fn longitude(&self) -> f64 {
         self.longitude
     }
+
+    /// Distance from the user's point in meters, if it's known.
+    pub fn distance(&self) -> Option<f64> {
+        self.distance
+    }
+
+    /// Radius of the area of the place in meters, estimated by its bounding box.
+    pub fn radius(&self) -> Option<f64> {
+        self.bbox.map(|bbox| geo::distance((bbox.south, bbox.west), (bbox.north, bbox.east)) / 2.0)
+    }
+
+    /// `Some` for suggestions of autocomplete APIs, which must be resolved before their coordinates are used.
+    pub fn as_suggestion(&self) -> Option<Suggestion> {
+        match (self.suggestion, self.provider, &self.place_id) {
+            (true, Some(provider), Some(place_id)) => Some(Suggestion {
+                provider,
+                place_id: place_id.clone(),
+                text: self.address.clone().unwrap_or_default(),
+            }),
+            _ => None
+        }
+    }
+
+    /// Prepend the name of the place (e.g. taken from a link) to its address.
+    pub fn with_name(self, name: &str) -> Location {
+        let address = match self.address {
+            Some(address) if !address.starts_with(name) => format!("{name}, {address}"),
+            Some(address) => address,
+            None => name.to_owned(),
+        };
+        Location { address: Some(address), ..self }
+    }
+
+    /// Whether both locations were returned by the same provider for the same place.
+    fn is_same_place(&self, other: &Location) -> bool {
+        self.provider.is_some() && self.provider == other.provider
+            && self.place_id.is_some() && self.place_id == other.place_id
+    }
+
+    /// Complement the location with the data of its duplicate. The coordinates of the more confident one are kept.
+    fn absorb(&mut self, duplicate: Location) {
+        if duplicate.confidence.unwrap_or_default() > self.confidence.unwrap_or_default() {
+            self.latitude = duplicate.latitude;
+            self.longitude = duplicate.longitude;
+            self.confidence = duplicate.confidence;
+            self.bbox = duplicate.bbox.or(self.bbox);
+        }
+        self.address = self.address.take().or(duplicate.address);
+        self.bbox = self.bbox.or(duplicate.bbox);
+        if self.kind == PlaceKind::Other {
+            self.kind = duplicate.kind;
+        }
+        self.components.fill_from(duplicate.components);
+    }
 }
 
 pub type LocResult = Result<Vec<Location>, anyhow::Error>;
@@ -58,48 +306,164 @@ pub trait LocFinder : Sync + Send {
 }
 
 pub struct SearchChain {
-    global_finders: Vec<DynLocFinder>,
-    regional_finders: HashMap<String, Vec<DynLocFinder>>,
+    routes: Routes<LocFinderChainWrapper>,
+    mode: SearchChainMode,
+    deadline: Duration,
+    cache: Option<Arc<ResultCache>>,
+}
+
+/// Results of the finders of a chain. The lookup is complete if every finder has answered, even with nothing.
+struct Lookup {
+    locations: Vec<Location>,
+    complete: bool,
 }
 
 impl SearchChain {
     pub fn new(global_finders: Vec<LocFinderChainWrapper>) -> SearchChain {
         let global_finders = global_finders.into_iter()
-            .filter_map(LocFinderChainWrapper::unwrap_if_not_disabled)
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
             .collect();
         SearchChain {
-            global_finders,
-            regional_finders: HashMap::new()
+            routes: Routes::new(global_finders),
+            mode: *SEARCH_CHAIN_MODE,
+            deadline: *SEARCH_CHAIN_DEADLINE,
+            cache: None,
         }
     }
 
+    pub fn with_cache(self, cache: Option<Arc<ResultCache>>) -> Self {
+        SearchChain { cache, ..self }
+    }
+
     pub fn for_lang_code(mut self, lc: &str, finders: Vec<LocFinderChainWrapper>) -> Self {
-        let mut finders = finders.into_iter()
-            .filter_map(LocFinderChainWrapper::unwrap_if_not_disabled)
-            .collect::<Vec<DynLocFinder>>();
-        self.regional_finders
-            .entry(lc.to_string())
-            .or_insert(Vec::with_capacity(finders.len()))
-            .append(&mut finders);
+        let finders = finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        self.routes.add_language(lc, finders);
+        self
+    }
+
+    pub fn for_country_code(mut self, cc: &str, finders: Vec<LocFinderChainWrapper>) -> Self {
+        let finders = finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        self.routes.add_country(cc, finders);
         self
     }
 
     #[tracing::instrument(skip(self), fields(query, lang_code))]
     pub async fn find(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> Vec<Location> {
-        let futures = self.regional_finders.get(lang_code)
-            .unwrap_or(&self.global_finders)
-            .iter()
+        let cached = match &self.cache {
+            Some(cache) => cache.get(query, lang_code, location).await,
+            None => None,
+        };
+        // the results are cached before ranking, since the distances depend on the exact location of the user
+        let locations = match cached {
+            Some(locations) => locations,
+            None => {
+                let finders = self.routes.select(lang_code, location);
+                let lookup = Self::lookup(self.mode, self.deadline, finders, query, lang_code, location).await;
+                if lookup.locations.is_empty() && !lookup.complete {
+                    self.find_stale(finders, query, lang_code, location).await
+                } else {
+                    if let Some(cache) = &self.cache {
+                        cache.put(query, lang_code, location, &lookup.locations, lookup.complete).await;
+                    }
+                    lookup.locations
+                }
+            }
+        };
+        ranking::rank(locations, location)
+    }
+
+    /// Answer from the responses of the providers that are kept in the HTTP cache past their freshness, when no provider
+    /// managed to answer. The stale results are not put into the result cache; a live lookup is started in the background
+    /// instead to refresh them.
+    async fn find_stale(&self, finders: &[LocFinderChainWrapper], query: &str, lang_code: &str, location: Option<(f64, f64)>) -> Vec<Location> {
+        let futures = finders.iter()
+            .map(|f| cache::stale_lookup(f.finder.find(query, lang_code, location)));
+        let results = join_all(futures).await.into_iter()
+            .filter_map(|res| res.inspect_err(|err| tracing::debug!("no stale response: {err}")).ok());
+        let locations = match self.mode {
+            SearchChainMode::Sequential => results.into_iter().find(|res| !res.is_empty()).unwrap_or_default(),
+            SearchChainMode::Parallel => merge::dedup(results.flatten().collect()),
+        };
+        if locations.is_empty() {
+            return locations
+        }
+
+        tracing::warn!("serving {} stale result(s)", locations.len());
+        STALE_RESULTS_COUNTER.inc_by(locations.len() as f64);
+        self.spawn_refresh(finders.to_vec(), query.to_owned(), lang_code.to_owned(), location);
+        locations
+    }
+
+    fn spawn_refresh(&self, finders: Vec<LocFinderChainWrapper>, query: String, lang_code: String, location: Option<(f64, f64)>) {
+        let (mode, deadline, cache) = (self.mode, self.deadline, self.cache.clone());
+        tokio::spawn(async move {
+            // all replicas serving the same stale results refresh them once per interval, if the result cache is enabled
+            if let Some(cache) = &cache && !cache.claim_refresh(&query, &lang_code, location).await {
+                tracing::debug!("the refresh of the stale results for '{query}' is skipped");
+                return
+            }
+            // the providers have just failed, so they're given some time to recover
+            tokio::time::sleep(*STALE_REFRESH_DELAY).await;
+            // the responses are put into the HTTP cache by the finders themselves
+            let lookup = Self::lookup(mode, deadline, &finders, &query, &lang_code, location).await;
+            if lookup.locations.is_empty() {
+                tracing::info!("couldn't refresh the stale results for '{query}'");
+            } else if let Some(cache) = cache {
+                cache.put(&query, &lang_code, location, &lookup.locations, lookup.complete).await;
+            }
+        });
+    }
+
+    async fn lookup(mode: SearchChainMode, deadline: Duration, finders: &[LocFinderChainWrapper], query: &str, lang_code: &str, location: Option<(f64, f64)>) -> Lookup {
+        match mode {
+            SearchChainMode::Sequential => Self::find_sequentially(finders, query, lang_code, location).await,
+            SearchChainMode::Parallel => Self::find_in_parallel(deadline, finders, query, lang_code, location).await,
+        }
+    }
+
+    async fn find_sequentially(finders: &[LocFinderChainWrapper], query: &str, lang_code: &str, location: Option<(f64, f64)>) -> Lookup {
+        let futures = finders.iter()
             .map(|f| f.find(query, lang_code, location));
 
+        let mut complete = true;
         for fut in futures {
             match fut.await {
-                Ok(res) if !res.is_empty() => return res,
+                Ok(res) if !res.is_empty() => return Lookup { locations: res, complete: true },
                 Ok(_) => continue,
-                Err(err) => tracing::error!("couldn't fetch loc data: {err}"),
+                Err(err) => {
+                    complete = false;
+                    log_finder_error(err)
+                },
             }
         };
 
-        Vec::default()
+        Lookup { locations: Vec::default(), complete }
+    }
+
+    async fn find_in_parallel(deadline: Duration, finders: &[LocFinderChainWrapper], query: &str, lang_code: &str, location: Option<(f64, f64)>) -> Lookup {
+        let futures = finders.iter()
+            .map(|f| tokio::time::timeout(deadline, f.find(query, lang_code, location)));
+
+        let mut locations = Vec::new();
+        let mut complete = true;
+        for res in join_all(futures).await {
+            match res {
+                Ok(Ok(mut res)) => locations.append(&mut res),
+                Ok(Err(err)) => {
+                    complete = false;
+                    log_finder_error(err)
+                },
+                Err(_) => {
+                    complete = false;
+                    tracing::warn!("a finder didn't manage to respond in {:?}", deadline)
+                },
+            }
+        }
+        Lookup { locations: merge::dedup(locations), complete }
     }
 }
 
@@ -107,21 +471,64 @@ pub fn finder(env: &str, instance: impl LocFinder + 'static) -> LocFinderChainWr
     LocFinderChainWrapper::wrap(env, Arc::new(instance))
 }
 
-#[derive(Clone)]
-pub struct LocFinderChainWrapper {
+/// Wrap a finder that is able to search both by a query and by coordinates into a pair of wrappers sharing the same instance.
+pub fn finder_with_reverse<T>(env: &str, instance: Arc<T>) -> (LocFinderChainWrapper, ReverseLocFinderChainWrapper)
+where
+    T: LocFinder + ReverseLocFinder + 'static
+{
+    let search: DynLocFinder = instance.clone();
+    let reverse: DynReverseLocFinder = instance;
+    let wrapper = LocFinderChainWrapper::wrap(env, search);
+    let reverse_wrapper = wrapper.sibling("REVERSE", reverse);
+    (wrapper, reverse_wrapper)
+}
+
+/// Decorates a finder with a switch to disable it by an environment variable, a timeout and a circuit breaker.
+pub struct LocFinderChainWrapper<F: ?Sized = dyn LocFinder> {
     env_suffix: String,
-    finder: DynLocFinder
+    finder: Arc<F>,
+    breaker: Arc<CircuitBreaker>,
+    timeout: Option<Duration>,
 }
 
-impl LocFinderChainWrapper {
-    pub fn wrap(env_suffix: &str, finder: DynLocFinder) -> Self {
+impl<F: ?Sized> Clone for LocFinderChainWrapper<F> {
+    fn clone(&self) -> Self {
+        LocFinderChainWrapper {
+            env_suffix: self.env_suffix.clone(),
+            finder: self.finder.clone(),
+            breaker: self.breaker.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<F: ?Sized> LocFinderChainWrapper<F> {
+    pub fn wrap(env_suffix: &str, finder: Arc<F>) -> Self {
         LocFinderChainWrapper {
             env_suffix: env_suffix.to_owned(),
-            finder
+            finder,
+            breaker: Arc::new(CircuitBreaker::new(env_suffix)),
+            timeout: None,
+        }
+    }
+
+    /// Wrap another interface of the same provider. It's disabled by the same variable, but has its own circuit breaker,
+    /// since the APIs behind the interfaces have separate keys and quotas, and an exhausted one mustn't turn off the others.
+    fn sibling<T: ?Sized>(&self, api: &str, finder: Arc<T>) -> LocFinderChainWrapper<T> {
+        LocFinderChainWrapper {
+            env_suffix: self.env_suffix.clone(),
+            finder,
+            breaker: Arc::new(CircuitBreaker::new(&format!("{}_{api}", self.env_suffix))),
+            timeout: self.timeout,
         }
     }
 
-    fn unwrap_if_not_disabled(self) -> Option<DynLocFinder> {
+    /// A call that takes longer is aborted and counted as a failure of the provider.
+    pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        LocFinderChainWrapper { timeout, ..self }
+    }
+
+    fn if_not_disabled(self) -> Option<Self> {
         let disabled = std::env::var(DISABLE_ENV_PREFIX.to_owned() + self.env_suffix.as_str())
             .map(|v| v == "true" || v == "1" || v == "yes" || v == "y")
             .unwrap_or(false);
@@ -129,7 +536,75 @@ impl LocFinderChainWrapper {
             tracing::warn!("The {} finder is disabled!", self.env_suffix);
             None
         } else {
-            Some(self.finder)
+            Some(self)
+        }
+    }
+
+    async fn guarded(&self, call: BoxFuture<'_, LocResult>) -> LocResult {
+        if !self.breaker.try_acquire() {
+            return Err(CircuitOpen(self.env_suffix.clone()).into())
+        }
+        let guard = CallGuard::start(&self.breaker);
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, call).await
+                .unwrap_or_else(|_| Err(FinderTimeout(self.env_suffix.clone(), timeout).into())),
+            None => call.await,
+        };
+        match &result {
+            Ok(_) => guard.finish(true),
+            Err(err) if is_provider_unusable(err) => guard.trip(),
+            Err(err) => guard.finish(!is_provider_failure(err)),
+        }
+        result
+    }
+}
+
+impl LocFinderChainWrapper {
+    async fn find(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> LocResult {
+        self.guarded(self.finder.find(query, lang_code, location)).await
+    }
+}
+
+impl ReverseLocFinderChainWrapper {
+    async fn find_by_coords(&self, latitude: f64, longitude: f64, lang_code: &str) -> LocResult {
+        self.guarded(self.finder.find_by_coords(latitude, longitude, lang_code)).await
+    }
+}
+
+impl NearbyLocFinderChainWrapper {
+    async fn find_nearby(&self, category: Category, query: &str, lang_code: &str, location: (f64, f64)) -> LocResult {
+        self.guarded(self.finder.find_nearby(category, query, lang_code, location)).await
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("the '{0}' finder didn't respond in {1:?}")]
+struct FinderTimeout(String, Duration);
+
+/// Errors that are not caused by the provider itself and must not affect its circuit breaker.
+/// A rejected request is the fault of the query, not of the provider.
+fn is_provider_failure(err: &anyhow::Error) -> bool {
+    !err.is::<CircuitOpen>() && !is_budget_exceeded(err) && !is_queue_full(err)
+        && ErrorClass::of(err) != Some(ErrorClass::InvalidRequest)
+}
+
+/// Errors after which there is no point in calling the provider again until the cooldown is over. Providers with key pools
+/// report them only when all keys of the pool are sidelined, since a rejected key is replaced by the next one.
+fn is_provider_unusable(err: &anyhow::Error) -> bool {
+    matches!(ErrorClass::of(err), Some(ErrorClass::QuotaExceeded | ErrorClass::AuthFailure))
+}
+
+fn log_finder_error(err: anyhow::Error) {
+    if err.is::<CircuitOpen>() {
+        tracing::debug!("skipping the finder: {err}");
+    } else if is_budget_exceeded(&err) || is_queue_full(&err) {
+        tracing::warn!("skipping the finder: {err}");
+    } else {
+        match ErrorClass::of(&err) {
+            Some(ErrorClass::QuotaExceeded) => tracing::warn!("skipping the finder: {err}"),
+            Some(ErrorClass::InvalidRequest) => tracing::warn!("the request was rejected: {err}"),
+            Some(ErrorClass::AuthFailure) => tracing::error!("the finder is misconfigured: {err}"),
+            Some(ErrorClass::Transient) | None => tracing::error!("couldn't fetch loc data: {err}"),
         }
     }
 }
@@ -0,0 +1,97 @@
+use once_cell::sync::Lazy;
+use async_trait::async_trait;
+use strum_macros::{AsRefStr, EnumIter};
+use super::{log_finder_error, ranking, LocFinderChainWrapper, Location, LocResult};
+use super::routing::Routes;
+
+const ENV_NEARBY_RADIUS_METERS: &str = "NEARBY_RADIUS_METERS";
+const DEFAULT_NEARBY_RADIUS_METERS: u32 = 1500;
+
+static NEARBY_RADIUS: Lazy<f64> = Lazy::new(|| {
+    let val: u32 = std::env::var(ENV_NEARBY_RADIUS_METERS)
+        .ok()
+        .and_then(|v| v.parse().map_err(|e| tracing::error!("couldn't parse {ENV_NEARBY_RADIUS_METERS}: {e}")).ok())
+        .unwrap_or(DEFAULT_NEARBY_RADIUS_METERS);
+    tracing::info!("{ENV_NEARBY_RADIUS_METERS} is {val}");
+    f64::from(val)
+});
+
+pub type NearbyLocFinderChainWrapper = LocFinderChainWrapper<dyn NearbyLocFinder>;
+
+/// Kinds of places searched around the user instead of geocoding the query. The names are used as the keys of
+/// the vocabulary in the locales: `category.{name}`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, AsRefStr, EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Category {
+    Pharmacy,
+    Cafe,
+    Restaurant,
+    Bar,
+    Atm,
+    Bank,
+    FuelStation,
+    Supermarket,
+    Hospital,
+    Hotel,
+    Parking,
+}
+
+/// Search for places of a category around a point. The original query is passed for the providers that search by text.
+#[async_trait]
+pub trait NearbyLocFinder : Sync + Send {
+    async fn find_nearby(&self, category: Category, query: &str, lang_code: &str, location: (f64, f64)) -> LocResult;
+}
+
+/// Radius of the nearby search in meters.
+pub(super) fn radius() -> f64 {
+    *NEARBY_RADIUS
+}
+
+pub struct NearbySearchChain {
+    routes: Routes<NearbyLocFinderChainWrapper>,
+}
+
+impl NearbySearchChain {
+    pub fn new(global_finders: Vec<NearbyLocFinderChainWrapper>) -> NearbySearchChain {
+        let global_finders = global_finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        NearbySearchChain {
+            routes: Routes::new(global_finders),
+        }
+    }
+
+    pub fn for_lang_code(mut self, lc: &str, finders: Vec<NearbyLocFinderChainWrapper>) -> Self {
+        let finders = finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        self.routes.add_language(lc, finders);
+        self
+    }
+
+    pub fn for_country_code(mut self, cc: &str, finders: Vec<NearbyLocFinderChainWrapper>) -> Self {
+        let finders = finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        self.routes.add_country(cc, finders);
+        self
+    }
+
+    /// The first finder that has found something wins. The results are sorted by the distance from the point.
+    #[tracing::instrument(skip(self))]
+    pub async fn find(&self, category: Category, query: &str, lang_code: &str, location: (f64, f64)) -> Vec<Location> {
+        let futures = self.routes.select(lang_code, Some(location))
+            .iter()
+            .map(|f| f.find_nearby(category, query, lang_code, location));
+
+        for fut in futures {
+            match fut.await {
+                Ok(res) if !res.is_empty() => return ranking::rank(res, Some(location)),
+                Ok(_) => continue,
+                Err(err) => log_finder_error(err),
+            }
+        };
+
+        Vec::default()
+    }
+}
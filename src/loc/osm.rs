@@ -1,14 +1,33 @@
+use std::time::Duration;
 use async_trait::async_trait;
 use reqwest::header::{ACCEPT_LANGUAGE, USER_AGENT};
 use reqwest_middleware::ClientWithMiddleware;
 use prometheus::Opts;
+use serde::Deserialize;
 use super::cache::WithCachedResponseCounters;
-use super::{cache, LocFinder, LocResult, Location, get_bounds, SEARCH_RADIUS};
+use super::errors::check_response;
+use super::reverse::ReverseLocFinder;
+use super::throttle::ThrottleMiddleware;
+use super::{cache, geo, ranking, response, routing, AddressComponents, BoundingBox, LocFinder, LocResult, Location, PlaceKind, Provider, get_bounds, SEARCH_RADIUS};
 use crate::metrics;
 use crate::redis::REDIS;
 
+const ENV_NOMINATIM_BASE_URL: &str = "NOMINATIM_BASE_URL";
+const ENV_NOMINATIM_USER_AGENT: &str = "NOMINATIM_USER_AGENT";
+const ENV_NOMINATIM_MIN_INTERVAL_MS: &str = "NOMINATIM_MIN_INTERVAL_MS";
+const ENV_NOMINATIM_MAX_WAIT_MS: &str = "NOMINATIM_MAX_WAIT_MS";
+
+const PUBLIC_NOMINATIM_BASE_URL: &str = "https://nominatim.openstreetmap.org";
+const DEFAULT_NOMINATIM_USER_AGENT: &str = "kozalosev/LocPlaceBot";
+/// The usage policy of the public instance allows one request per second at most.
+const PUBLIC_NOMINATIM_MIN_INTERVAL_MS: u64 = 1000;
+const DEFAULT_NOMINATIM_MAX_WAIT_MS: u64 = 3000;
+const RESULTS_LIMIT: u8 = 10;
+
 pub struct OpenStreetMapLocFinder {
     client: ClientWithMiddleware,
+    base_url: String,
+    user_agent: String,
 
     api_req_counter: prometheus::Counter,
     cached_resp_counter: prometheus::Counter,
@@ -16,7 +35,29 @@ pub struct OpenStreetMapLocFinder {
 }
 
 impl OpenStreetMapLocFinder {
-    pub fn new() -> OpenStreetMapLocFinder {
+    /// Requests to the public instance are throttled by default; a self-hosted one is not throttled unless
+    /// `NOMINATIM_MIN_INTERVAL_MS` is set. Requests that would wait for a slot longer than `NOMINATIM_MAX_WAIT_MS` are skipped.
+    pub fn from_env() -> OpenStreetMapLocFinder {
+        let base_url = std::env::var(ENV_NOMINATIM_BASE_URL)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| PUBLIC_NOMINATIM_BASE_URL.to_owned())
+            .trim_end_matches('/')
+            .to_owned();
+        tracing::info!("{ENV_NOMINATIM_BASE_URL} is {base_url}");
+        let user_agent = user_agent();
+        let default_interval = if base_url == PUBLIC_NOMINATIM_BASE_URL { PUBLIC_NOMINATIM_MIN_INTERVAL_MS } else { 0 };
+        let interval: u64 = cache::get_env_or_default(ENV_NOMINATIM_MIN_INTERVAL_MS, default_interval);
+        tracing::info!("{ENV_NOMINATIM_MIN_INTERVAL_MS} is {interval}");
+
+        let mut client = cache::caching_client_builder(Provider::OpenStreetMap, &REDIS.pool);
+        if interval > 0 {
+            let max_wait: u64 = cache::get_env_or_default(ENV_NOMINATIM_MAX_WAIT_MS, DEFAULT_NOMINATIM_MAX_WAIT_MS);
+            tracing::info!("{ENV_NOMINATIM_MAX_WAIT_MS} is {max_wait}");
+            client = client.with(ThrottleMiddleware::new(&REDIS.pool, "nominatim",
+                Duration::from_millis(interval), Duration::from_millis(max_wait)));
+        }
+
         let api_req_opts = Opts::new("open_street_map_api_requests_total", "count of requests to the OpenStreetMap API");
 
         let resp_opts = Opts::new("open_street_map_api_responses_total", "count of responses from the OpenStreetMap API split by the source");
@@ -24,7 +65,9 @@ impl OpenStreetMapLocFinder {
         let from_remote_opts = resp_opts.const_label("source", "remote");
 
         OpenStreetMapLocFinder {
-            client: cache::caching_client(&REDIS.pool),
+            client: client.build(),
+            base_url,
+            user_agent,
 
             api_req_counter: metrics::REGISTRY.register_counter("OpenStreetMap API requests", api_req_opts),
             cached_resp_counter: metrics::REGISTRY.register_counter("OpenStreetMap API requests", from_cache_opts),
@@ -33,35 +76,86 @@ impl OpenStreetMapLocFinder {
     }
 }
 
+/// The User-Agent identifying the application, as required by the usage policies of both Nominatim and Photon.
+pub(super) fn user_agent() -> String {
+    std::env::var(ENV_NOMINATIM_USER_AGENT)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_NOMINATIM_USER_AGENT.to_owned())
+}
+
 #[async_trait]
 impl LocFinder for OpenStreetMapLocFinder {
     #[tracing::instrument(skip(self))]
     async fn find(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> LocResult {
         self.api_req_counter.inc();
-        let viewbox_part = location
-            .map(|loc| get_bounds(loc, *SEARCH_RADIUS))
-            .map(|(p1, p2)| format!("&viewbox={},{},{},{}", p1.1, p1.0, p2.1, p2.0))
-            .unwrap_or_default();
-        let query = urlencoding::encode(query);
-        let url = format!("https://nominatim.openstreetmap.org/search?q={query}&format=json{viewbox_part}");
+        let url = format!("{}/search?{}", self.base_url, search_params(query, location, ranking::max_result_distance()));
         tracing::debug!("Request: {url}");
         let resp = self.client.get(url)
-            .header(USER_AGENT, "kozalosev/LocPlaceBot")
+            .header(USER_AGENT, &self.user_agent)
             .header(ACCEPT_LANGUAGE, lang_code)
             .send().await?;
         self.inc_resp_counter(&resp);
+        let resp = check_response(Provider::OpenStreetMap, resp).await?;
+
+        let body = resp.bytes().await?;
+        tracing::info!("Response from Open Street Map Nominatim API: {}", String::from_utf8_lossy(&body));
+        parse_search_response(&body)
+    }
+}
 
-        let json = resp.json::<serde_json::Value>().await?;
-        tracing::info!("Response from Open Street Map Nominatim API: {json}");
+#[async_trait]
+impl ReverseLocFinder for OpenStreetMapLocFinder {
+    #[tracing::instrument(skip(self))]
+    async fn find_by_coords(&self, latitude: f64, longitude: f64, lang_code: &str) -> LocResult {
+        self.api_req_counter.inc();
+        let url = format!("{}/reverse?lat={latitude}&lon={longitude}&format=jsonv2&addressdetails=1", self.base_url);
+        tracing::debug!("Request: {url}");
+        let resp = self.client.get(url)
+            .header(USER_AGENT, &self.user_agent)
+            .header(ACCEPT_LANGUAGE, lang_code)
+            .send().await?;
+        self.inc_resp_counter(&resp);
+        let resp = check_response(Provider::OpenStreetMap, resp).await?;
 
-        let empty = Vec::new();
-        let results = json.as_array().unwrap_or(&empty).iter()
-            .filter_map(map_resp)
-            .collect();
-        Ok(results)
+        let body = resp.bytes().await?;
+        tracing::info!("Response from Open Street Map Nominatim API (reverse): {}", String::from_utf8_lossy(&body));
+        parse_reverse_response(&body)
     }
 }
 
+/// The query string of the search endpoint. The location of the user is a preference by default. If the distance
+/// of the results is limited, the search is bounded by the area and the countries it covers, since farther
+/// results would be dropped by the ranking anyway.
+pub(super) fn search_params(query: &str, location: Option<(f64, f64)>, max_distance: Option<f64>) -> String {
+    let query = urlencoding::encode(query);
+    let mut params = format!("q={query}&format=jsonv2&addressdetails=1&limit={RESULTS_LIMIT}");
+    let Some(location) = location else {
+        return params
+    };
+
+    let Some(max_distance) = max_distance else {
+        let (p1, p2) = get_bounds(location, *SEARCH_RADIUS);
+        params.push_str(&format!("&viewbox={},{},{},{}", p1.1, p1.0, p2.1, p2.0));
+        return params
+    };
+
+    let bbox = geo::bounding_box(location, max_distance);
+    params.push_str(&format!("&viewbox={},{},{},{}&bounded=1", bbox.west, bbox.south, bbox.east, bbox.north));
+    // the countries are sampled on a 3x3 grid over the box: at the center, corners and midpoints of the edges
+    let mut country_codes: Vec<String> = [bbox.south, location.0, bbox.north].into_iter()
+        .flat_map(|lat| [bbox.west, location.1, bbox.east].map(|lon| (lat, lon)))
+        .filter_map(|(lat, lon)| routing::country_code(lat, lon))
+        .map(str::to_lowercase)
+        .collect();
+    country_codes.sort();
+    country_codes.dedup();
+    if !country_codes.is_empty() {
+        params.push_str(&format!("&countrycodes={}", country_codes.join(",")));
+    }
+    params
+}
+
 impl WithCachedResponseCounters for OpenStreetMapLocFinder {
     fn cached_resp_counter(&self) -> &prometheus::Counter {
         &self.cached_resp_counter
@@ -72,13 +166,111 @@ impl WithCachedResponseCounters for OpenStreetMapLocFinder {
     }
 }
 
-fn map_resp(v: &serde_json::Value) -> Option<Location> {
-    let address = Some(v["display_name"].as_str()?.to_string());
+#[derive(Deserialize)]
+struct Place {
+    display_name: String,
+    #[serde(deserialize_with = "response::from_str")]
+    lat: f64,
+    #[serde(deserialize_with = "response::from_str")]
+    lon: f64,
+    osm_type: Option<String>,
+    osm_id: Option<u64>,
+    #[serde(default)]
+    addresstype: String,
+    #[serde(default)]
+    category: String,
+    name: Option<String>,
+    importance: Option<f64>,
+    /// `[south, north, west, east]`
+    boundingbox: Option<[String; 4]>,
+    #[serde(default)]
+    address: Address,
+}
+
+#[derive(Deserialize, Default)]
+struct Address {
+    country: Option<String>,
+    state: Option<String>,
+    region: Option<String>,
+    city: Option<String>,
+    town: Option<String>,
+    village: Option<String>,
+    hamlet: Option<String>,
+    road: Option<String>,
+    house_number: Option<String>,
+    postcode: Option<String>,
+}
+
+/// The reverse endpoint returns `{"error": "..."}` instead of a place if nothing was found.
+#[derive(Deserialize)]
+struct ReverseError {
+    error: Option<String>,
+}
+
+pub(super) fn parse_search_response(body: &[u8]) -> LocResult {
+    let places: Vec<Place> = response::parse(Provider::OpenStreetMap, "search", body)?;
+    let results = places.into_iter()
+        .map(map_place)
+        .collect();
+    Ok(results)
+}
 
-    let latitude: f64 = v["lat"].as_str()?.parse().ok()?;
-    let longitude: f64 = v["lon"].as_str()?.parse().ok()?;
+pub(super) fn parse_reverse_response(body: &[u8]) -> LocResult {
+    if let ReverseError { error: Some(error) } = response::parse(Provider::OpenStreetMap, "reverse", body)? {
+        tracing::debug!("nothing was found: {error}");
+        return Ok(Vec::default())
+    }
+    let place: Place = response::parse(Provider::OpenStreetMap, "reverse", body)?;
+    Ok(vec![map_place(place)])
+}
+
+fn map_place(place: Place) -> Location {
+    let place_id = match (place.osm_type, place.osm_id) {
+        (Some(osm_type), Some(osm_id)) => Some(format!("{osm_type}/{osm_id}")),
+        _ => None
+    };
+    let kind = map_kind(&place.addresstype, &place.category);
+    let addr = place.address;
+    let components = AddressComponents {
+        // stations, airports, rivers and the like aren't parts of an address, so they're named as well as POIs
+        name: place.name.filter(|name| !name.is_empty() && matches!(kind, PlaceKind::Poi | PlaceKind::Other)),
+        country: addr.country,
+        region: addr.state.or(addr.region),
+        city: addr.city.or(addr.town).or(addr.village).or(addr.hamlet),
+        street: addr.road,
+        house: addr.house_number,
+        postcode: addr.postcode,
+    };
+
+    Location {
+        address: Some(place.display_name),
+        provider: Some(Provider::OpenStreetMap),
+        place_id,
+        kind,
+        confidence: place.importance,
+        bbox: place.boundingbox.as_ref().and_then(map_bounding_box),
+        components,
+        ..Location::new(place.lat, place.lon)
+    }
+}
+
+fn map_kind(address_type: &str, category: &str) -> PlaceKind {
+    match (address_type, category) {
+        ("house" | "building", _) => PlaceKind::House,
+        ("road", _) => PlaceKind::Street,
+        ("city" | "town" | "village" | "hamlet", _) => PlaceKind::City,
+        ("state" | "region" | "province", _) => PlaceKind::Region,
+        ("country", _) => PlaceKind::Country,
+        (_, "amenity" | "shop" | "tourism" | "leisure" | "office" | "historic" | "craft") => PlaceKind::Poi,
+        _ => PlaceKind::Other
+    }
+}
 
-    Some(Location {
-        address, latitude, longitude
+fn map_bounding_box([south, north, west, east]: &[String; 4]) -> Option<BoundingBox> {
+    Some(BoundingBox {
+        south: south.parse().ok()?,
+        north: north.parse().ok()?,
+        west: west.parse().ok()?,
+        east: east.parse().ok()?,
     })
 }
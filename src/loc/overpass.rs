@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use async_trait::async_trait;
+use reqwest::header::USER_AGENT;
+use reqwest_middleware::ClientWithMiddleware;
+use prometheus::Opts;
+use serde::Deserialize;
+use super::cache::WithCachedResponseCounters;
+use super::errors::check_response;
+use super::nearby::{self, Category, NearbyLocFinder};
+use super::{cache, response, AddressComponents, LocResult, Location, PlaceKind, Provider};
+use crate::metrics;
+use crate::redis::REDIS;
+
+const ENV_OVERPASS_BASE_URL: &str = "OVERPASS_BASE_URL";
+const DEFAULT_OVERPASS_BASE_URL: &str = "https://overpass-api.de";
+const RESULTS_LIMIT: u8 = 20;
+const QUERY_TIMEOUT_SECS: u8 = 10;
+
+/// Searches for places of OpenStreetMap by their tags around a point.
+pub struct OverpassLocFinder {
+    client: ClientWithMiddleware,
+    base_url: String,
+
+    api_req_counter: prometheus::Counter,
+    cached_resp_counter: prometheus::Counter,
+    fetched_resp_counter: prometheus::Counter
+}
+
+impl OverpassLocFinder {
+    pub fn from_env() -> OverpassLocFinder {
+        let base_url = std::env::var(ENV_OVERPASS_BASE_URL)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_OVERPASS_BASE_URL.to_owned())
+            .trim_end_matches('/')
+            .to_owned();
+        tracing::info!("{ENV_OVERPASS_BASE_URL} is {base_url}");
+
+        let api_req_opts = Opts::new("overpass_api_requests_total", "count of requests to the Overpass API");
+
+        let resp_opts = Opts::new("overpass_api_responses_total", "count of responses from the Overpass API split by the source");
+        let from_cache_opts = resp_opts.clone().const_label("source", "cache");
+        let from_remote_opts = resp_opts.const_label("source", "remote");
+
+        OverpassLocFinder {
+            client: cache::caching_client(Provider::OpenStreetMap, &REDIS.pool),
+            base_url,
+
+            api_req_counter: metrics::REGISTRY.register_counter("Overpass API requests", api_req_opts),
+            cached_resp_counter: metrics::REGISTRY.register_counter("Overpass API requests", from_cache_opts),
+            fetched_resp_counter: metrics::REGISTRY.register_counter("Overpass API requests", from_remote_opts),
+        }
+    }
+}
+
+#[async_trait]
+impl NearbyLocFinder for OverpassLocFinder {
+    #[tracing::instrument(skip(self))]
+    async fn find_nearby(&self, category: Category, query: &str, lang_code: &str, location: (f64, f64)) -> LocResult {
+        self.api_req_counter.inc();
+        let data = build_query(category, location, nearby::radius());
+        let url = format!("{}/api/interpreter?data={}", self.base_url, urlencoding::encode(&data));
+        tracing::debug!("Request: {url}");
+        let resp = self.client.get(url)
+            .header(USER_AGENT, "kozalosev/LocPlaceBot")
+            .send().await?;
+        self.inc_resp_counter(&resp);
+        let resp = check_response(Provider::OpenStreetMap, resp).await?;
+
+        let body = resp.bytes().await?;
+        tracing::info!("Response from Overpass API: {}", String::from_utf8_lossy(&body));
+        parse_response(&body, lang_code, query)
+    }
+}
+
+impl WithCachedResponseCounters for OverpassLocFinder {
+    fn cached_resp_counter(&self) -> &prometheus::Counter {
+        &self.cached_resp_counter
+    }
+
+    fn fetched_resp_counter(&self) -> &prometheus::Counter {
+        &self.fetched_resp_counter
+    }
+}
+
+/// Nodes, ways and relations having any of the tags of the category; ways and relations are returned with their centers.
+pub(super) fn build_query(category: Category, (lat, lon): (f64, f64), radius: f64) -> String {
+    let statements: String = map_category(category).iter()
+        .map(|(key, value)| format!(r#"nwr["{key}"="{value}"](around:{radius},{lat},{lon});"#))
+        .collect();
+    format!("[out:json][timeout:{QUERY_TIMEOUT_SECS}];({statements});out center {RESULTS_LIMIT};")
+}
+
+fn map_category(category: Category) -> &'static [(&'static str, &'static str)] {
+    match category {
+        Category::Pharmacy => &[("amenity", "pharmacy")],
+        Category::Cafe => &[("amenity", "cafe")],
+        Category::Restaurant => &[("amenity", "restaurant"), ("amenity", "fast_food")],
+        Category::Bar => &[("amenity", "bar"), ("amenity", "pub")],
+        Category::Atm => &[("amenity", "atm")],
+        Category::Bank => &[("amenity", "bank")],
+        Category::FuelStation => &[("amenity", "fuel")],
+        Category::Supermarket => &[("shop", "supermarket")],
+        Category::Hospital => &[("amenity", "hospital"), ("amenity", "clinic")],
+        Category::Hotel => &[("tourism", "hotel"), ("tourism", "hostel")],
+        Category::Parking => &[("amenity", "parking")],
+    }
+}
+
+#[derive(Deserialize)]
+struct OverpassResponse {
+    #[serde(default)]
+    elements: Vec<Element>,
+}
+
+/// Nodes have coordinates, ways and relations have centers.
+#[derive(Deserialize)]
+struct Element {
+    #[serde(rename = "type")]
+    element_type: String,
+    id: u64,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    center: Option<Center>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Center {
+    lat: f64,
+    lon: f64,
+}
+
+/// Elements without a name are named by the query, since the user is looking for any place of the category.
+pub(super) fn parse_response(body: &[u8], lang_code: &str, query: &str) -> LocResult {
+    let resp: OverpassResponse = response::parse(Provider::OpenStreetMap, "overpass", body)?;
+    let results = resp.elements.into_iter()
+        .filter_map(|element| map_element(element, lang_code, query))
+        .collect();
+    Ok(results)
+}
+
+fn map_element(element: Element, lang_code: &str, query: &str) -> Option<Location> {
+    let (latitude, longitude) = match (element.lat, element.lon, &element.center) {
+        (Some(lat), Some(lon), _) => (lat, lon),
+        (_, _, Some(center)) => (center.lat, center.lon),
+        _ => return None
+    };
+    let tag = |key: &str| element.tags.get(key).filter(|v| !v.is_empty()).cloned();
+    let name = tag(&format!("name:{lang_code}")).or_else(|| tag("name"));
+    let components = AddressComponents {
+        name: name.clone(),
+        country: None,
+        region: None,
+        city: tag("addr:city"),
+        street: tag("addr:street"),
+        house: tag("addr:housenumber"),
+        postcode: tag("addr:postcode"),
+    };
+    let street = match (&components.street, &components.house) {
+        (Some(street), Some(house)) => Some(format!("{street}, {house}")),
+        (street, _) => street.clone(),
+    };
+    let address = [name.or_else(|| Some(query.to_owned())), street, components.city.clone()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Some(Location {
+        address: Some(address),
+        provider: Some(Provider::OpenStreetMap),
+        place_id: Some(format!("{}/{}", element.element_type, element.id)),
+        kind: PlaceKind::Poi,
+        components,
+        ..Location::new(latitude, longitude)
+    })
+}
@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use reqwest::header::USER_AGENT;
+use reqwest_middleware::ClientWithMiddleware;
+use prometheus::Opts;
+use super::cache::WithCachedResponseCounters;
+use super::errors::check_response;
+use super::osm;
+use super::{cache, AddressComponents, BoundingBox, LocFinder, LocResult, Location, PlaceKind, Provider};
+use crate::metrics;
+use crate::redis::REDIS;
+
+const ENV_PHOTON_BASE_URL: &str = "PHOTON_BASE_URL";
+const DEFAULT_PHOTON_BASE_URL: &str = "https://photon.komoot.io";
+const RESULTS_LIMIT: u8 = 10;
+
+/// The public instance rejects requests with other languages, so the parameter is omitted for them.
+const SUPPORTED_LANGUAGES: [&str; 4] = ["en", "de", "fr", "it"];
+
+pub struct PhotonLocFinder {
+    client: ClientWithMiddleware,
+    base_url: String,
+    user_agent: String,
+
+    api_req_counter: prometheus::Counter,
+    cached_resp_counter: prometheus::Counter,
+    fetched_resp_counter: prometheus::Counter
+}
+
+impl PhotonLocFinder {
+    pub fn from_env() -> PhotonLocFinder {
+        let base_url = std::env::var(ENV_PHOTON_BASE_URL)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_PHOTON_BASE_URL.to_owned())
+            .trim_end_matches('/')
+            .to_owned();
+        tracing::info!("{ENV_PHOTON_BASE_URL} is {base_url}");
+
+        let api_req_opts = Opts::new("photon_api_requests_total", "count of requests to the Photon API");
+
+        let resp_opts = Opts::new("photon_api_responses_total", "count of responses from the Photon API split by the source");
+        let from_cache_opts = resp_opts.clone().const_label("source", "cache");
+        let from_remote_opts = resp_opts.const_label("source", "remote");
+
+        PhotonLocFinder {
+            client: cache::caching_client(Provider::Photon, &REDIS.pool),
+            base_url,
+            user_agent: osm::user_agent(),
+
+            api_req_counter: metrics::REGISTRY.register_counter("Photon API requests", api_req_opts),
+            cached_resp_counter: metrics::REGISTRY.register_counter("Photon API requests", from_cache_opts),
+            fetched_resp_counter: metrics::REGISTRY.register_counter("Photon API requests", from_remote_opts),
+        }
+    }
+}
+
+#[async_trait]
+impl LocFinder for PhotonLocFinder {
+    #[tracing::instrument(skip(self))]
+    async fn find(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> LocResult {
+        self.api_req_counter.inc();
+        let bias_part = location
+            .map(|(lat, lon)| format!("&lat={lat}&lon={lon}"))
+            .unwrap_or_default();
+        let lang_part = Some(lang_code)
+            .filter(|lc| SUPPORTED_LANGUAGES.contains(lc))
+            .map(|lc| format!("&lang={lc}"))
+            .unwrap_or_default();
+        let query = urlencoding::encode(query);
+        let url = format!("{}/api/?q={query}&limit={RESULTS_LIMIT}{lang_part}{bias_part}", self.base_url);
+        tracing::debug!("Request: {url}");
+        let resp = self.client.get(url)
+            .header(USER_AGENT, &self.user_agent)
+            .send().await?;
+        self.inc_resp_counter(&resp);
+        let resp = check_response(Provider::Photon, resp).await?;
+
+        let json = resp.json::<serde_json::Value>().await?;
+        tracing::info!("Response from Photon API: {json}");
+
+        let empty = Vec::new();
+        let results = json["features"].as_array().unwrap_or(&empty).iter()
+            .filter_map(map_feature)
+            .collect();
+        Ok(results)
+    }
+}
+
+impl WithCachedResponseCounters for PhotonLocFinder {
+    fn cached_resp_counter(&self) -> &prometheus::Counter {
+        &self.cached_resp_counter
+    }
+
+    fn fetched_resp_counter(&self) -> &prometheus::Counter {
+        &self.fetched_resp_counter
+    }
+}
+
+/// Map a GeoJSON feature. Photon doesn't return a formatted address, so it's composed from the properties.
+pub(super) fn map_feature(v: &serde_json::Value) -> Option<Location> {
+    let coords = v["geometry"]["coordinates"].as_array()?;
+    let longitude = coords.first()?.as_f64()?;
+    let latitude = coords.get(1)?.as_f64()?;
+
+    let props = &v["properties"];
+    let prop = |name: &str| props[name].as_str().filter(|s| !s.is_empty());
+    let street = match (prop("street"), prop("housenumber")) {
+        (Some(street), Some(number)) => Some(format!("{street}, {number}")),
+        (street, _) => street.map(str::to_owned),
+    };
+    let mut parts: Vec<String> = Vec::new();
+    let candidates = [prop("name").map(str::to_owned), street]
+        .into_iter()
+        .chain(["city", "state", "country"].map(|name| prop(name).map(str::to_owned)))
+        .flatten();
+    for part in candidates {
+        if !parts.contains(&part) {
+            parts.push(part);
+        }
+    }
+    let address = Some(parts.join(", ")).filter(|addr| !addr.is_empty());
+
+    let place_id = match (prop("osm_type"), props["osm_id"].as_u64()) {
+        (Some(osm_type), Some(osm_id)) => Some(format!("{osm_type}/{osm_id}")),
+        _ => None
+    };
+    let kind = map_kind(prop("type").unwrap_or_default(), prop("osm_key").unwrap_or_default());
+    // the extent is [west, north, east, south]
+    let extent = &props["extent"];
+    let bbox = match (extent[0].as_f64(), extent[1].as_f64(), extent[2].as_f64(), extent[3].as_f64()) {
+        (Some(west), Some(north), Some(east), Some(south)) => Some(BoundingBox { south, west, north, east }),
+        _ => None
+    };
+    let components = AddressComponents {
+        name: prop("name").filter(|_| kind == PlaceKind::Poi).map(str::to_owned),
+        country: prop("country").map(str::to_owned),
+        region: prop("state").map(str::to_owned),
+        city: prop("city").map(str::to_owned),
+        street: prop("street").map(str::to_owned),
+        house: prop("housenumber").map(str::to_owned),
+        postcode: prop("postcode").map(str::to_owned),
+    };
+
+    Some(Location {
+        address,
+        provider: Some(Provider::Photon),
+        place_id,
+        kind,
+        bbox,
+        components,
+        ..Location::new(latitude, longitude)
+    })
+}
+
+fn map_kind(place_type: &str, osm_key: &str) -> PlaceKind {
+    match (place_type, osm_key) {
+        ("house", "amenity" | "shop" | "tourism" | "leisure" | "office" | "historic" | "craft") => PlaceKind::Poi,
+        ("house", _) => PlaceKind::House,
+        ("street", _) => PlaceKind::Street,
+        ("city" | "locality" | "district", _) => PlaceKind::City,
+        ("county" | "state", _) => PlaceKind::Region,
+        ("country", _) => PlaceKind::Country,
+        (_, "amenity" | "shop" | "tourism" | "leisure" | "office" | "historic" | "craft") => PlaceKind::Poi,
+        _ => PlaceKind::Other
+    }
+}
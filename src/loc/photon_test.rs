@@ -0,0 +1,33 @@
+use serde_json::json;
+use super::photon::map_feature;
+
+#[test]
+fn test_map_feature() {
+    let feature = json!({
+        "type": "Feature",
+        "geometry": { "type": "Point", "coordinates": [13.3888599, 52.5170365] },
+        "properties": {
+            "name": "Berlin",
+            "city": "Berlin",
+            "state": "Berlin",
+            "country": "Germany",
+        }
+    });
+    let loc = map_feature(&feature).expect("the feature must be mapped");
+    assert_eq!(loc.address(), Some("Berlin, Germany".to_owned()));
+    assert_eq!(loc.latitude(), 52.5170365);
+    assert_eq!(loc.longitude(), 13.3888599);
+
+    let feature = json!({
+        "geometry": { "coordinates": [37.6176, 55.7558] },
+        "properties": { "street": "Tverskaya", "housenumber": "1", "city": "Moscow" }
+    });
+    let loc = map_feature(&feature).expect("the feature must be mapped");
+    assert_eq!(loc.address(), Some("Tverskaya, 1, Moscow".to_owned()));
+}
+
+#[test]
+fn test_map_feature_without_geometry() {
+    let feature = json!({ "properties": { "name": "Nowhere" } });
+    assert!(map_feature(&feature).is_none());
+}
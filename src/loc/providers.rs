@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use super::config::SearchChainConfig;
+use super::google::GoogleLocFinder;
+use super::nearby::{NearbyLocFinder, NearbyLocFinderChainWrapper, NearbySearchChain};
+use super::osm::OpenStreetMapLocFinder;
+use super::overpass::OverpassLocFinder;
+use super::photon::PhotonLocFinder;
+use super::gazetteer::GazetteerLocFinder;
+use super::result_cache::ResultCache;
+use super::reverse::{ReverseLocFinderChainWrapper, ReverseSearchChain};
+use super::suggest::{Suggestion, SuggestionResolver};
+use super::yandex::YandexLocFinder;
+use super::{finder, finder_with_reverse, LocFinderChainWrapper, Location, Provider, SearchChain};
+use crate::redis::REDIS;
+
+pub struct FinderChains {
+    pub search: SearchChain,
+    pub reverse: ReverseSearchChain,
+    pub nearby: NearbySearchChain,
+}
+
+/// All finders are created once, so that their counters, caches and circuit breakers survive reloads of the config.
+/// Only the chains are rebuilt.
+pub struct Providers {
+    google: Arc<GoogleLocFinder>,
+    yandex: Arc<YandexLocFinder>,
+    search: HashMap<Provider, LocFinderChainWrapper>,
+    reverse: HashMap<Provider, ReverseLocFinderChainWrapper>,
+    nearby: HashMap<Provider, NearbyLocFinderChainWrapper>,
+    result_cache: Option<Arc<ResultCache>>,
+    resolvers: HashMap<Provider, Arc<dyn SuggestionResolver>>,
+}
+
+impl Providers {
+    pub fn from_env() -> Providers {
+        let google = Arc::new(GoogleLocFinder::from_env());
+        let yandex = Arc::new(YandexLocFinder::from_env());
+        let (osm, osm_reverse) = finder_with_reverse("OSM", Arc::new(OpenStreetMapLocFinder::from_env()));
+        let (yandex_finder, yandex_reverse) = finder_with_reverse("YANDEX", yandex.clone());
+        let (google_finder, google_reverse) = finder_with_reverse("GOOGLE", google.clone());
+        // Overpass is a separate service, so it's disabled by its own variable
+        let nearby = HashMap::from([
+            (Provider::Google, google_finder.sibling("NEARBY", google.clone() as Arc<dyn NearbyLocFinder>)),
+            (Provider::Yandex, yandex_finder.sibling("NEARBY", yandex.clone() as Arc<dyn NearbyLocFinder>)),
+            (Provider::OpenStreetMap, LocFinderChainWrapper::wrap("OVERPASS", Arc::new(OverpassLocFinder::from_env()) as Arc<dyn NearbyLocFinder>)),
+        ]);
+
+        let mut search = HashMap::from([
+            (Provider::Google, google_finder),
+            (Provider::Yandex, yandex_finder),
+            (Provider::OpenStreetMap, osm),
+            (Provider::Photon, finder("PHOTON", PhotonLocFinder::from_env())),
+        ]);
+        // the last resort working without network, if a GeoNames dump is provided
+        if let Some(gazetteer) = GazetteerLocFinder::from_env() {
+            search.insert(Provider::Gazetteer, finder("GAZETTEER", gazetteer));
+        }
+        let reverse = HashMap::from([
+            (Provider::Google, google_reverse),
+            (Provider::Yandex, yandex_reverse),
+            (Provider::OpenStreetMap, osm_reverse),
+        ]);
+
+        let result_cache = ResultCache::from_env(&REDIS.pool).map(Arc::new);
+        let resolvers = HashMap::from([
+            (Provider::Google, google.clone() as Arc<dyn SuggestionResolver>),
+            (Provider::Yandex, yandex.clone() as Arc<dyn SuggestionResolver>),
+        ]);
+
+        Providers { google, yandex, search, reverse, nearby, result_cache, resolvers }
+    }
+
+    /// Build the chains and switch the modes of the finders; the default modes are used if the config doesn't set them.
+    pub fn chains(&self, config: &SearchChainConfig) -> FinderChains {
+        let default_chain = config.default_chain();
+        let mut search = SearchChain::new(self.search_finders(config, &default_chain))
+            .with_cache(self.result_cache.clone());
+        let mut reverse = ReverseSearchChain::new(self.reverse_finders(config, &default_chain));
+        let mut nearby = NearbySearchChain::new(self.nearby_finders(config, &default_chain));
+        for (lang_code, chain) in config.language_chains() {
+            search = search.for_lang_code(lang_code, self.search_finders(config, &chain));
+            reverse = reverse.for_lang_code(lang_code, self.reverse_finders(config, &chain));
+            nearby = nearby.for_lang_code(lang_code, self.nearby_finders(config, &chain));
+        }
+        for (country_code, chain) in config.country_chains() {
+            search = search.for_country_code(country_code, self.search_finders(config, &chain));
+            reverse = reverse.for_country_code(country_code, self.reverse_finders(config, &chain));
+            nearby = nearby.for_country_code(country_code, self.nearby_finders(config, &chain));
+        }
+
+        // a mode removed from the config on reload must not be kept
+        self.yandex.set_mode(config.mode(Provider::Yandex).unwrap_or_default());
+        self.google.set_mode(config.mode(Provider::Google).unwrap_or_default());
+        FinderChains { search, reverse, nearby }
+    }
+
+    /// Fetch the place of a suggestion chosen by the user. Errors are logged, and `None` is returned for them.
+    pub async fn resolve(&self, suggestion: &Suggestion, lang_code: &str) -> Option<Location> {
+        let Some(resolver) = self.resolvers.get(&suggestion.provider) else {
+            tracing::error!("{:?} cannot resolve suggestions", suggestion.provider);
+            return None
+        };
+        match resolver.resolve(suggestion, lang_code).await {
+            Ok(locations) => locations.into_iter().next(),
+            Err(err) => {
+                tracing::error!("couldn't resolve the suggestion {suggestion:?}: {err}");
+                None
+            }
+        }
+    }
+
+    fn search_finders(&self, config: &SearchChainConfig, chain: &[Provider]) -> Vec<LocFinderChainWrapper> {
+        Self::wrappers(&self.search, config, chain)
+    }
+
+    fn reverse_finders(&self, config: &SearchChainConfig, chain: &[Provider]) -> Vec<ReverseLocFinderChainWrapper> {
+        Self::wrappers(&self.reverse, config, chain)
+    }
+
+    fn nearby_finders(&self, config: &SearchChainConfig, chain: &[Provider]) -> Vec<NearbyLocFinderChainWrapper> {
+        Self::wrappers(&self.nearby, config, chain)
+    }
+
+    /// Providers that are not available (like the gazetteer without a dump) or don't support the kind of search are skipped.
+    fn wrappers<F: ?Sized>(finders: &HashMap<Provider, LocFinderChainWrapper<F>>, config: &SearchChainConfig, chain: &[Provider]) -> Vec<LocFinderChainWrapper<F>> {
+        chain.iter()
+            .filter_map(|provider| finders.get(provider)
+                .map(|wrapper| wrapper.clone().with_timeout(config.timeout(*provider))))
+            .collect()
+    }
+}
@@ -0,0 +1,43 @@
+use once_cell::sync::Lazy;
+use super::{geo, Location};
+
+const ENV_MAX_RESULT_DISTANCE_METERS: &str = "MAX_RESULT_DISTANCE_METERS";
+
+static MAX_RESULT_DISTANCE: Lazy<Option<f64>> = Lazy::new(|| {
+    let val = std::env::var(ENV_MAX_RESULT_DISTANCE_METERS)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .and_then(|v| v.parse().map_err(|e| tracing::error!("couldn't parse {ENV_MAX_RESULT_DISTANCE_METERS}: {e}")).ok());
+    tracing::info!("{ENV_MAX_RESULT_DISTANCE_METERS} is {val:?}");
+    val
+});
+
+/// Order the results by the distance from the user's point, the closest first.
+/// Candidates farther than `MAX_RESULT_DISTANCE_METERS` are dropped if the limit is set.
+pub fn rank(locations: Vec<Location>, origin: Option<(f64, f64)>) -> Vec<Location> {
+    rank_within(locations, origin, *MAX_RESULT_DISTANCE)
+}
+
+pub(super) fn max_result_distance() -> Option<f64> {
+    *MAX_RESULT_DISTANCE
+}
+
+pub(super) fn rank_within(locations: Vec<Location>, origin: Option<(f64, f64)>, max_distance: Option<f64>) -> Vec<Location> {
+    let Some(origin) = origin else {
+        return locations
+    };
+    let mut locations: Vec<Location> = locations.into_iter()
+        // the distance of suggestions is reported by the provider, if at all
+        .map(|loc| if loc.suggestion {
+            loc
+        } else {
+            Location {
+                distance: Some(geo::distance(origin, (loc.latitude, loc.longitude))),
+                ..loc
+            }
+        })
+        .filter(|loc| max_distance.is_none_or(|max| loc.distance.unwrap_or_default() <= max))
+        .collect();
+    locations.sort_by(|a, b| a.distance.unwrap_or(f64::MAX).total_cmp(&b.distance.unwrap_or(f64::MAX)));
+    locations
+}
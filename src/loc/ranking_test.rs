@@ -0,0 +1,53 @@
+use super::Location;
+use super::ranking::rank_within;
+
+const USER_POINT: (f64, f64) = (55.751244, 37.618423);
+
+#[test]
+fn test_rank_orders_by_distance() {
+    let locations = vec![
+        location("Far", 59.939095, 30.315868),
+        location("Near", 55.752023, 37.617499),
+        location("Middle", 55.796127, 37.537840),
+    ];
+
+    let ranked = rank_within(locations, Some(USER_POINT), None);
+    let addresses: Vec<String> = ranked.iter()
+        .filter_map(Location::address)
+        .collect();
+    assert_eq!(addresses, vec!["Near", "Middle", "Far"]);
+
+    let nearest_distance = ranked.first().and_then(Location::distance).unwrap();
+    assert!((100.0..120.0).contains(&nearest_distance), "distance: {nearest_distance}");
+}
+
+#[test]
+fn test_rank_drops_far_candidates() {
+    let locations = vec![
+        location("Far", 59.939095, 30.315868),
+        location("Near", 55.752023, 37.617499),
+    ];
+
+    let ranked = rank_within(locations, Some(USER_POINT), Some(10_000.0));
+    assert_eq!(ranked.len(), 1);
+    assert_eq!(ranked[0].address.as_deref(), Some("Near"));
+}
+
+#[test]
+fn test_rank_without_user_point() {
+    let locations = vec![
+        location("Far", 59.939095, 30.315868),
+        location("Near", 55.752023, 37.617499),
+    ];
+
+    let ranked = rank_within(locations, None, Some(10_000.0));
+    assert_eq!(ranked.len(), 2);
+    assert!(ranked.iter().all(|loc| loc.distance.is_none()));
+}
+
+fn location(address: &str, latitude: f64, longitude: f64) -> Location {
+    Location {
+        address: Some(address.to_string()),
+        ..Location::new(latitude, longitude)
+    }
+}
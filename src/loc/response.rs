@@ -0,0 +1,49 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use once_cell::sync::Lazy;
+use prometheus::Opts;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use super::Provider;
+use crate::metrics;
+
+static MALFORMED_RESPONSES_COUNTER: Lazy<prometheus::CounterVec> = Lazy::new(|| {
+    let opts = Opts::new("loc_malformed_responses_total", "count of responses from the providers that don't match the expected models");
+    metrics::REGISTRY.register_counter_vec("malformed responses", opts, &["provider", "API"])
+});
+
+#[derive(Debug, thiserror::Error)]
+#[error("the response of {provider:?} ({api}) doesn't match the model at '{path}': {message}")]
+pub struct MalformedResponse {
+    provider: Provider,
+    api: &'static str,
+    path: String,
+    message: String,
+}
+
+/// Deserialize the body into the model of the API. A mismatch is counted, and the error keeps the path to the offending field
+/// to be logged by the search chain.
+pub(super) fn parse<T: DeserializeOwned>(provider: Provider, api: &'static str, body: &[u8]) -> Result<T, MalformedResponse> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        MALFORMED_RESPONSES_COUNTER.with_label_values(&[provider.as_ref(), api]).inc();
+        MalformedResponse {
+            provider,
+            api,
+            path: err.path().to_string(),
+            message: err.into_inner().to_string(),
+        }
+    })
+}
+
+/// Some providers return numbers as strings, like Nominatim does with coordinates.
+pub(super) fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,139 @@
+use mobc::Pool;
+use once_cell::sync::Lazy;
+use mobc_redis::redis::{self, AsyncCommands};
+use mobc_redis::RedisConnectionManager;
+use prometheus::Opts;
+use super::cache::get_env_or_default;
+use super::Location;
+use crate::metrics;
+
+const REDIS_KEY_PREFIX: &str = "loc-results";
+
+const ENV_RESULT_CACHE_TTL_SECS: &str = "RESULT_CACHE_TTL_SECS";
+const ENV_RESULT_CACHE_NEGATIVE_TTL_SECS: &str = "RESULT_CACHE_NEGATIVE_TTL_SECS";
+const ENV_RESULT_CACHE_GRID_DEGREES: &str = "RESULT_CACHE_GRID_DEGREES";
+const ENV_STALE_REFRESH_INTERVAL_SECS: &str = "STALE_REFRESH_INTERVAL_SECS";
+
+const DEFAULT_RESULT_CACHE_TTL_SECS: u64 = 3600;
+const DEFAULT_RESULT_CACHE_NEGATIVE_TTL_SECS: u64 = 300;
+const DEFAULT_RESULT_CACHE_GRID_DEGREES: f64 = 0.1;    // about 11 km along a meridian
+const DEFAULT_STALE_REFRESH_INTERVAL_SECS: u64 = 300;
+
+static LOOKUP_COUNTER: Lazy<prometheus::CounterVec> = Lazy::new(|| {
+    let opts = Opts::new("loc_result_cache_requests_total", "count of lookups in the cache of search results split by the outcome");
+    metrics::REGISTRY.register_counter_vec("result cache lookups", opts, &["result"])
+});
+
+/// Caches the merged results of the whole search chain, unlike `loc::cache`, which caches separate HTTP responses.
+///
+/// The key consists of the normalized query, the language and the cell of a coarse grid the user's location falls in,
+/// so queries differing only in case or whitespace and users living nearby share the same entries.
+/// Empty results are cached for a shorter time, but only if all finders have answered.
+pub struct ResultCache {
+    pool: Pool<RedisConnectionManager>,
+    ttl: u64,
+    negative_ttl: u64,
+    grid: f64,
+    refresh_interval: u64,
+}
+
+impl ResultCache {
+    /// Returns `None` if the cache is disabled by `RESULT_CACHE_TTL_SECS=0`.
+    pub fn from_env(pool: &Pool<RedisConnectionManager>) -> Option<ResultCache> {
+        let ttl = get_env_or_default(ENV_RESULT_CACHE_TTL_SECS, DEFAULT_RESULT_CACHE_TTL_SECS);
+        let negative_ttl = get_env_or_default(ENV_RESULT_CACHE_NEGATIVE_TTL_SECS, DEFAULT_RESULT_CACHE_NEGATIVE_TTL_SECS);
+        let grid = get_env_or_default(ENV_RESULT_CACHE_GRID_DEGREES, DEFAULT_RESULT_CACHE_GRID_DEGREES);
+        let refresh_interval = get_env_or_default(ENV_STALE_REFRESH_INTERVAL_SECS, DEFAULT_STALE_REFRESH_INTERVAL_SECS);
+        tracing::info!("{ENV_RESULT_CACHE_TTL_SECS} is {ttl}, {ENV_RESULT_CACHE_NEGATIVE_TTL_SECS} is {negative_ttl}, {ENV_RESULT_CACHE_GRID_DEGREES} is {grid}");
+        tracing::info!("{ENV_STALE_REFRESH_INTERVAL_SECS} is {refresh_interval}");
+        if ttl == 0 {
+            return None
+        }
+
+        Some(ResultCache {
+            pool: pool.clone(),
+            ttl,
+            negative_ttl,
+            grid: if grid > 0.0 { grid } else { DEFAULT_RESULT_CACHE_GRID_DEGREES },
+            refresh_interval: refresh_interval.max(1),
+        })
+    }
+
+    /// Errors of Redis are logged and treated as misses.
+    pub async fn get(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> Option<Vec<Location>> {
+        let key = build_key(query, lang_code, location, self.grid);
+        let result: Result<Option<Vec<u8>>, String> = match self.pool.get().await {
+            Ok(mut conn) => conn.get::<_, Option<Vec<u8>>>(&key).await.map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        let locations = result
+            .inspect_err(|err| tracing::error!("couldn't fetch search results from the cache: {err}"))
+            .ok().flatten()
+            .and_then(|bytes| postcard::from_bytes::<Vec<Location>>(&bytes)
+                .inspect_err(|err| tracing::error!("couldn't deserialize cached search results: {err}"))
+                .ok());
+        let result = match &locations {
+            Some(locations) if locations.is_empty() => "negative-hit",
+            Some(_) => "hit",
+            None => "miss",
+        };
+        LOOKUP_COUNTER.with_label_values(&[result]).inc();
+        locations
+    }
+
+    /// `complete` tells whether all finders have answered. Otherwise, an empty result may be caused by failures and is not stored.
+    pub async fn put(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>, locations: &[Location], complete: bool) {
+        let ttl = if locations.is_empty() { self.negative_ttl } else { self.ttl };
+        if ttl == 0 || (locations.is_empty() && !complete) {
+            return
+        }
+        let data = match postcard::to_stdvec(locations) {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::error!("couldn't serialize search results: {err}");
+                return
+            }
+        };
+
+        let key = build_key(query, lang_code, location, self.grid);
+        let result = match self.pool.get().await {
+            Ok(mut conn) => conn.set_ex::<_, _, ()>(key, data, ttl).await.map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        if let Err(err) = result {
+            tracing::error!("couldn't store search results in the cache: {err}");
+        }
+    }
+
+    /// Claim the background refresh of stale results for all replicas. `false` if the refresh of the same entry has been
+    /// claimed within `STALE_REFRESH_INTERVAL_SECS` already, or if Redis is unavailable.
+    pub async fn claim_refresh(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> bool {
+        let key = build_key(query, lang_code, location, self.grid) + ":refresh";
+        let options = redis::SetOptions::default()
+            .conditional_set(redis::ExistenceCheck::NX)
+            .with_expiration(redis::SetExpiry::EX(self.refresh_interval));
+        let result = match self.pool.get().await {
+            Ok(mut conn) => conn.set_options::<_, _, Option<String>>(key, 1, options).await.map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        result
+            .inspect_err(|err| tracing::error!("couldn't claim the refresh of stale results: {err}"))
+            .is_ok_and(|reply| reply.is_some())
+    }
+}
+
+pub(super) fn build_key(query: &str, lang_code: &str, location: Option<(f64, f64)>, grid: f64) -> String {
+    let cell = location
+        .map(|(lat, lon)| format!("{}:{}", (lat / grid).floor(), (lon / grid).floor()))
+        .unwrap_or_else(|| "any".to_owned());
+    let query_hash = sha256::digest(normalize_query(query));
+    format!("{REDIS_KEY_PREFIX}:{lang_code}:{cell}:{query_hash}")
+}
+
+/// Lowercase the query and collapse the whitespace.
+pub(super) fn normalize_query(query: &str) -> String {
+    query.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -0,0 +1,52 @@
+use super::Location;
+use super::result_cache::{build_key, normalize_query, ResultCache};
+use crate::testutils::start_redis;
+
+#[test]
+fn test_normalize_query() {
+    assert_eq!(normalize_query("  Red   Square,\tMoscow "), "red square, moscow");
+    assert_eq!(normalize_query("КРАСНАЯ площадь"), "красная площадь");
+}
+
+#[test]
+fn test_build_key() {
+    let key = build_key("Red Square", "en", Some((55.7539, 37.6208)), 0.1);
+    assert_eq!(key, build_key(" red  square ", "en", Some((55.7001, 37.6999)), 0.1));
+    assert_ne!(key, build_key("Red Square", "ru", Some((55.7539, 37.6208)), 0.1));
+    assert_ne!(key, build_key("Red Square", "en", Some((55.8539, 37.6208)), 0.1));
+    assert_ne!(key, build_key("Red Square", "en", None, 0.1));
+    assert!(key.starts_with("loc-results:en:557:376:"), "{key}");
+}
+
+#[tokio::test]
+async fn test_result_cache() {
+    let (_redis_container, redis_pool) = start_redis().await;
+    let cache = ResultCache::from_env(&redis_pool).expect("the cache is enabled by default");
+    let location = Some((55.75, 37.62));
+
+    cache.put("Red Square", "en", location, &[], false).await;
+    assert!(cache.get("Red Square", "en", location).await.is_none(), "incomplete empty results must not be cached");
+
+    cache.put("Red Square", "en", location, &[], true).await;
+    assert!(cache.get("Red Square", "en", location).await.is_some_and(|res| res.is_empty()));
+
+    let result = Location {
+        address: Some("Red Square, Moscow".to_string()),
+        ..Location::new(55.7539, 37.6208)
+    };
+    cache.put("Red Square", "en", location, &[result], true).await;
+    let cached = cache.get("red square", "en", location).await.expect("the results must be cached");
+    assert_eq!(cached.len(), 1);
+    assert_eq!(cached[0].address(), Some("Red Square, Moscow".to_string()));
+}
+
+#[tokio::test]
+async fn test_claim_refresh() {
+    let (_redis_container, redis_pool) = start_redis().await;
+    let cache = ResultCache::from_env(&redis_pool).expect("the cache is enabled by default");
+    let location = Some((55.75, 37.62));
+
+    assert!(cache.claim_refresh("Red Square", "en", location).await);
+    assert!(!cache.claim_refresh("red  square", "en", Some((55.71, 37.61))).await, "the same entry must be refreshed once per interval");
+    assert!(cache.claim_refresh("Red Square", "ru", location).await);
+}
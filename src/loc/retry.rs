@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant, SystemTime};
+use async_trait::async_trait;
+use http::Extensions;
+use once_cell::sync::Lazy;
+use prometheus::Opts;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Method, Request, Response, StatusCode};
+use reqwest_middleware::{Middleware, Next};
+use crate::metrics;
+use super::cache::get_env_or_default;
+use super::Provider;
+
+const ENV_MAX_RETRIES: &str = "HTTP_MAX_RETRIES";
+const ENV_RETRY_BASE_DELAY_MS: &str = "HTTP_RETRY_BASE_DELAY_MS";
+const ENV_RETRY_BUDGET_MS: &str = "HTTP_RETRY_BUDGET_MS";
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 100;
+const DEFAULT_RETRY_BUDGET_MS: u64 = 2000;
+
+static SETTINGS: Lazy<RetrySettings> = Lazy::new(|| {
+    let settings = RetrySettings {
+        max_retries: get_env_or_default(ENV_MAX_RETRIES, DEFAULT_MAX_RETRIES),
+        base_delay: Duration::from_millis(get_env_or_default(ENV_RETRY_BASE_DELAY_MS, DEFAULT_RETRY_BASE_DELAY_MS)),
+        budget: Duration::from_millis(get_env_or_default(ENV_RETRY_BUDGET_MS, DEFAULT_RETRY_BUDGET_MS)),
+    };
+    tracing::info!("retry settings: {settings:?}");
+    settings
+});
+
+static RETRIES_COUNTER: Lazy<prometheus::CounterVec> = Lazy::new(|| {
+    let opts = Opts::new("http_retries_total", "count of retried requests to the providers split by the reason");
+    metrics::REGISTRY.register_counter_vec("HTTP retries", opts, &["provider", "reason"])
+});
+
+#[derive(Debug, Copy, Clone)]
+pub struct RetrySettings {
+    pub max_retries: u32,
+    /// The delay before the first retry; it's doubled for each next one.
+    pub base_delay: Duration,
+    /// No retry is made if it would start later than this after the first attempt, and the retried attempts are
+    /// aborted when it's over, so that inline queries are answered in time.
+    pub budget: Duration,
+}
+
+/// Repeats idempotent requests that failed due to network errors or transient errors of the server, with jittered
+/// exponential backoff or after the delay requested by the server in `Retry-After`. Requests with other methods are
+/// retried only if they're marked by the [`SafeToRetry`] extension.
+/// It must be added after the cache, so that only requests to the network are retried.
+pub struct RetryMiddleware {
+    provider: Provider,
+    settings: RetrySettings,
+}
+
+/// Marks a request that doesn't change anything on the server despite its method, like the searches made by POST.
+#[derive(Debug, Copy, Clone)]
+pub struct SafeToRetry;
+
+impl RetryMiddleware {
+    pub fn from_env(provider: Provider) -> RetryMiddleware {
+        Self::new(provider, *SETTINGS)
+    }
+
+    pub fn new(provider: Provider, settings: RetrySettings) -> RetryMiddleware {
+        RetryMiddleware { provider, settings }
+    }
+
+    /// The reason to retry and the delay before the next attempt, or `None` if the result must be returned as is.
+    fn retry_delay(&self, result: &reqwest_middleware::Result<Response>, attempt: u32) -> Option<(String, Duration)> {
+        let backoff = jittered(self.settings.base_delay.saturating_mul(2u32.saturating_pow(attempt)));
+        match result {
+            Ok(resp) => match resp.status() {
+                // the quota may be exhausted for the rest of the day, so only the explicit permission to retry counts
+                StatusCode::TOO_MANY_REQUESTS => retry_after(resp)
+                    .map(|delay| (resp.status().as_u16().to_string(), delay)),
+                StatusCode::SERVICE_UNAVAILABLE => Some((resp.status().as_u16().to_string(), retry_after(resp).unwrap_or(backoff))),
+                StatusCode::REQUEST_TIMEOUT | StatusCode::INTERNAL_SERVER_ERROR | StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT =>
+                    Some((resp.status().as_u16().to_string(), backoff)),
+                _ => None,
+            },
+            Err(reqwest_middleware::Error::Reqwest(err)) if err.is_timeout() => Some(("timeout".to_owned(), backoff)),
+            Err(reqwest_middleware::Error::Reqwest(err)) if err.is_connect() || err.is_request() => Some(("network".to_owned(), backoff)),
+            Err(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RetryMiddleware {
+    async fn handle(&self, mut req: Request, extensions: &mut Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+        let start = Instant::now();
+        let is_retryable = is_idempotent(req.method()) || extensions.get::<SafeToRetry>().is_some();
+        let mut attempt = 0;
+        loop {
+            let retry_req = (attempt < self.settings.max_retries && is_retryable)
+                .then(|| req.try_clone())
+                .flatten();
+            let result = next.clone().run(req, extensions).await;
+            let Some(retry_req) = retry_req else {
+                return result
+            };
+            let Some((reason, delay)) = self.retry_delay(&result, attempt) else {
+                return result
+            };
+            if start.elapsed() + delay > self.settings.budget {
+                tracing::debug!("no time left to retry the request to {:?} ({reason})", self.provider);
+                return result
+            }
+
+            tracing::warn!("retrying the request to {:?} in {delay:?} ({reason})", self.provider);
+            RETRIES_COUNTER.with_label_values(&[self.provider.as_ref(), &reason]).inc();
+            drop(result);
+            tokio::time::sleep(delay).await;
+            req = retry_req;
+            // the retried attempt mustn't take the whole timeout of the client
+            let remaining = self.settings.budget.saturating_sub(start.elapsed());
+            let timeout = req.timeout_mut();
+            *timeout = Some(timeout.map_or(remaining, |timeout| timeout.min(remaining)));
+            attempt += 1;
+        }
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE)
+}
+
+/// A random delay between the half and the full backoff, so that the clients don't retry in lockstep.
+fn jittered(backoff: Duration) -> Duration {
+    let half = backoff / 2;
+    half + half.mul_f64(fastrand::f64())
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, SystemTime::now())
+}
+
+/// The value is either a number of seconds or an HTTP date.
+pub(super) fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    value.parse().map(Duration::from_secs).ok()
+        .or_else(|| httpdate::parse_http_date(value).ok()
+            .map(|date| date.duration_since(now).unwrap_or_default()))
+}
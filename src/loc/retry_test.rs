@@ -0,0 +1,109 @@
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+use reqwest::StatusCode;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use super::retry::{parse_retry_after, RetryMiddleware, RetrySettings, SafeToRetry};
+use super::Provider;
+
+const SETTINGS: RetrySettings = RetrySettings {
+    max_retries: 2,
+    base_delay: Duration::from_millis(10),
+    budget: Duration::from_secs(1),
+};
+
+#[test]
+fn test_parse_retry_after() {
+    let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+    assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(Duration::ZERO));
+    assert_eq!(parse_retry_after("soon", SystemTime::now()), None);
+}
+
+#[tokio::test]
+async fn test_transient_errors_are_retried() {
+    let addr = serve(vec![
+        "503 Service Unavailable\r\nRetry-After: 0",
+        "502 Bad Gateway",
+        "200 OK",
+    ]).await;
+    let resp = client(SETTINGS).get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // the number of retries is limited
+    let addr = serve(vec!["500 Internal Server Error"; 3]).await;
+    let resp = client(SETTINGS).get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn test_safe_posts_are_retried() {
+    let addr = serve(vec!["503 Service Unavailable", "200 OK"]).await;
+    let resp = client(SETTINGS).post(format!("http://{addr}/"))
+        .with_extension(SafeToRetry)
+        .body("{}")
+        .send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_requests_are_not_retried() {
+    // not idempotent
+    let addr = serve(vec!["503 Service Unavailable", "200 OK"]).await;
+    let resp = client(SETTINGS).post(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // the quota may be exhausted
+    let addr = serve(vec!["429 Too Many Requests", "200 OK"]).await;
+    let resp = client(SETTINGS).get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // the delay doesn't fit into the budget
+    let addr = serve(vec!["429 Too Many Requests\r\nRetry-After: 5", "200 OK"]).await;
+    let resp = client(SETTINGS).get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let addr = serve(vec!["400 Bad Request", "200 OK"]).await;
+    let resp = client(SETTINGS).get(format!("http://{addr}/")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_retried_attempt_is_limited_by_budget() {
+    // the server hangs on the second attempt, and the client itself has no timeout
+    let addr = serve(vec!["503 Service Unavailable", ""]).await;
+    let settings = RetrySettings { budget: Duration::from_millis(300), ..SETTINGS };
+    let result = tokio::time::timeout(Duration::from_secs(5), client(settings).get(format!("http://{addr}/")).send()).await
+        .expect("the retried attempt must be aborted when the budget is over");
+    assert!(result.is_err_and(|err| err.is_timeout()));
+}
+
+/// Responds to the connections with the statuses (and optional headers) one by one. An empty status means no response.
+async fn serve(responses: Vec<&'static str>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        for status in responses {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return
+            };
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await;
+            if status.is_empty() {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                return
+            }
+            let resp = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            let _ = socket.write_all(resp.as_bytes()).await;
+        }
+    });
+    addr
+}
+
+fn client(settings: RetrySettings) -> ClientWithMiddleware {
+    ClientBuilder::new(reqwest::Client::new())
+        .with(RetryMiddleware::new(Provider::Yandex, settings))
+        .build()
+}
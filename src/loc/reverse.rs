@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use super::{log_finder_error, LocFinderChainWrapper, Location, LocResult};
+use super::routing::Routes;
+
+pub type DynReverseLocFinder = Arc<dyn ReverseLocFinder>;
+pub type ReverseLocFinderChainWrapper = LocFinderChainWrapper<dyn ReverseLocFinder>;
+
+/// Resolve an address by coordinates. The most precise objects are expected to go first.
+#[async_trait]
+pub trait ReverseLocFinder : Sync + Send {
+    async fn find_by_coords(&self, latitude: f64, longitude: f64, lang_code: &str) -> LocResult;
+}
+
+pub struct ReverseSearchChain {
+    routes: Routes<ReverseLocFinderChainWrapper>,
+}
+
+impl ReverseSearchChain {
+    pub fn new(global_finders: Vec<ReverseLocFinderChainWrapper>) -> ReverseSearchChain {
+        let global_finders = global_finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        ReverseSearchChain {
+            routes: Routes::new(global_finders),
+        }
+    }
+
+    pub fn for_lang_code(mut self, lc: &str, finders: Vec<ReverseLocFinderChainWrapper>) -> Self {
+        let finders = finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        self.routes.add_language(lc, finders);
+        self
+    }
+
+    pub fn for_country_code(mut self, cc: &str, finders: Vec<ReverseLocFinderChainWrapper>) -> Self {
+        let finders = finders.into_iter()
+            .filter_map(LocFinderChainWrapper::if_not_disabled)
+            .collect();
+        self.routes.add_country(cc, finders);
+        self
+    }
+
+    /// The finders are chosen by the country of the point itself.
+    #[tracing::instrument(skip(self))]
+    pub async fn find(&self, latitude: f64, longitude: f64, lang_code: &str) -> Vec<Location> {
+        let futures = self.routes.select(lang_code, Some((latitude, longitude)))
+            .iter()
+            .map(|f| f.find_by_coords(latitude, longitude, lang_code));
+
+        for fut in futures {
+            match fut.await {
+                Ok(res) if !res.is_empty() => return res,
+                Ok(_) => continue,
+                Err(err) => log_finder_error(err),
+            }
+        };
+
+        Vec::default()
+    }
+
+    /// Attach the address and the rest of the details of the most precise object to the exact point requested by the user.
+    pub async fn locate(&self, latitude: f64, longitude: f64, lang_code: &str) -> Location {
+        let best_match = self.find(latitude, longitude, lang_code).await
+            .into_iter()
+            .find(|loc| loc.address.is_some());
+        match best_match {
+            Some(loc) => Location { latitude, longitude, ..loc },
+            None => Location::new(latitude, longitude),
+        }
+    }
+}
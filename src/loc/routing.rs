@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use country_boundaries::{CountryBoundaries, LatLon, BOUNDARIES_ODBL_360X180};
+use once_cell::sync::Lazy;
+
+/// Boundaries of the countries derived from OpenStreetMap data, © OpenStreetMap contributors, ODbL.
+static BOUNDARIES: Lazy<CountryBoundaries> = Lazy::new(|| CountryBoundaries::from_reader(BOUNDARIES_ODBL_360X180)
+    .expect("couldn't load the embedded country boundaries"));
+
+/// Load the boundaries at startup instead of the first request.
+pub fn preload_boundaries() {
+    let _ = *BOUNDARIES;
+}
+
+/// ISO 3166-1 alpha-2 code of the country the point belongs to, resolved offline.
+pub fn country_code(latitude: f64, longitude: f64) -> Option<&'static str> {
+    let position = LatLon::new(latitude, longitude).ok()?;
+    // the ids are sorted from the smallest area, so subdivisions like "US-TX" go before their countries
+    BOUNDARIES.ids(position)
+        .into_iter()
+        .find(|id| !id.contains('-'))
+}
+
+/// Chains of finders chosen by the country of the user's location or, if it's unknown, by the language.
+pub(super) struct Routes<W> {
+    default: Vec<W>,
+    languages: HashMap<String, Vec<W>>,
+    countries: HashMap<String, Vec<W>>,
+}
+
+impl<W> Routes<W> {
+    pub(super) fn new(default: Vec<W>) -> Routes<W> {
+        Routes {
+            default,
+            languages: HashMap::new(),
+            countries: HashMap::new(),
+        }
+    }
+
+    pub(super) fn add_language(&mut self, lang_code: &str, mut finders: Vec<W>) {
+        self.languages
+            .entry(lang_code.to_string())
+            .or_insert(Vec::with_capacity(finders.len()))
+            .append(&mut finders);
+    }
+
+    pub(super) fn add_country(&mut self, country_code: &str, mut finders: Vec<W>) {
+        self.countries
+            .entry(country_code.to_uppercase())
+            .or_insert(Vec::with_capacity(finders.len()))
+            .append(&mut finders);
+    }
+
+    /// The language is taken into account only if the location is unknown: a Russian-speaking user in Berlin
+    /// should get the same results as the locals.
+    pub(super) fn select(&self, lang_code: &str, location: Option<(f64, f64)>) -> &[W] {
+        let finders = match location {
+            Some((latitude, longitude)) => country_code(latitude, longitude)
+                .and_then(|country_code| self.countries.get(country_code)),
+            None => self.languages.get(lang_code),
+        };
+        finders.unwrap_or(&self.default)
+    }
+}
@@ -0,0 +1,24 @@
+use super::routing::{country_code, Routes};
+
+#[test]
+fn test_country_code() {
+    assert_eq!(country_code(55.7558, 37.6173), Some("RU"));
+    assert_eq!(country_code(52.5200, 13.4050), Some("DE"));
+    assert_eq!(country_code(32.7767, -96.7970), Some("US"));
+    assert_eq!(country_code(-45.0, -120.0), None);
+    assert_eq!(country_code(95.0, 0.0), None);
+}
+
+#[test]
+fn test_select() {
+    let mut routes = Routes::new(vec!["google"]);
+    routes.add_language("ru", vec!["yandex"]);
+    routes.add_country("ru", vec!["yandex", "google"]);
+
+    let moscow = Some((55.7558, 37.6173));
+    let berlin = Some((52.5200, 13.4050));
+    assert_eq!(routes.select("en", moscow), ["yandex", "google"]);
+    assert_eq!(routes.select("ru", berlin), ["google"]);
+    assert_eq!(routes.select("ru", None), ["yandex"]);
+    assert_eq!(routes.select("en", None), ["google"]);
+}
@@ -0,0 +1,36 @@
+use std::future::Future;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use super::{LocResult, Provider};
+
+tokio::task_local! {
+    static SESSION_TOKEN: String;
+}
+
+/// A place offered by an autocomplete API. It has no coordinates: they are fetched only for the suggestion the user picks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub provider: Provider,
+    pub place_id: String,
+    /// The text of the suggestion, to search by if the provider cannot resolve it by the identifier.
+    pub text: String,
+}
+
+/// Fetches the details of a suggestion picked by the user.
+#[async_trait]
+pub trait SuggestionResolver : Sync + Send {
+    async fn resolve(&self, suggestion: &Suggestion, lang_code: &str) -> LocResult;
+}
+
+/// Run the future within an autocomplete session. The requests for suggestions and the request for the details of the
+/// chosen one are billed as a single session if they carry the same token.
+pub async fn with_session_token<F: Future>(token: Option<String>, fut: F) -> F::Output {
+    match token {
+        Some(token) => SESSION_TOKEN.scope(token, fut).await,
+        None => fut.await,
+    }
+}
+
+pub(super) fn session_token() -> Option<String> {
+    SESSION_TOKEN.try_with(String::clone).ok()
+}
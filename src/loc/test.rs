@@ -1,10 +1,17 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::anyhow;
 use async_trait::async_trait;
 use crate::loc;
-use super::SearchChain;
+use super::{formatter, google, osm, overpass, yandex, BoundingBox, PlaceKind, Provider, SearchChain, SearchChainMode};
+use super::errors::ErrorClass;
+use super::nearby::Category;
+use super::response::MalformedResponse;
 use super::Location;
 use super::LocResult;
 use super::LocFinder;
+use super::reverse::{ReverseLocFinder, ReverseSearchChain};
 
 #[tokio::test]
 async fn test_search_chain() {
@@ -28,25 +35,364 @@ async fn test_search_chain() {
     }
 }
 
+#[tokio::test]
+async fn test_parallel_search_chain() {
+    let global_address = "123456 Global Test Land";
+    let duplicate_address = "Global Test Land, 123456";
+    let other_address = "Another Place";
+
+    let chain = SearchChain {
+        mode: SearchChainMode::Parallel,
+        deadline: Duration::from_millis(100),
+        ..SearchChain::new(vec![
+            stub_finder(vec![location(global_address)]),
+            failing_finder(),
+            stub_finder(vec![location(duplicate_address), location_at(other_address, 10.0, 20.0)]),
+            slow_finder(vec![location_at("Too Late", 30.0, 40.0)]),
+        ])
+    };
+
+    let result = chain.find("", "en", None).await;
+    let addresses: Vec<String> = result.iter()
+        .filter_map(Location::address)
+        .collect();
+    assert_eq!(addresses, vec![global_address, other_address]);
+}
+
+#[tokio::test]
+async fn test_reverse_search_chain() {
+    let global_address = "123456 Global Test Land";
+    let ru_address = "123456 Russia Test Land";
+
+    let ru_location = Location {
+        provider: Some(Provider::Yandex),
+        kind: PlaceKind::House,
+        components: loc::AddressComponents { city: Some("Test City".to_owned()), ..Default::default() },
+        ..location(ru_address)
+    };
+
+    let reverse_finder = |result| loc::LocFinderChainWrapper::<dyn ReverseLocFinder>::wrap("", Arc::new(StubLocFinder { result, delay: None, fail: false }));
+    let chain = ReverseSearchChain::new(vec![reverse_finder(vec![]), reverse_finder(vec![location(global_address)])])
+        .for_lang_code("ru", vec![reverse_finder(vec![ru_location.clone()])])
+        .for_country_code("RU", vec![reverse_finder(vec![ru_location])]);
+
+    // the chain is chosen by the country of the point, not by the language
+    for (lang_code, (lat, lon), address) in [("en", (55.7, 37.6), ru_address), ("ru", (52.5, 13.4), global_address)] {
+        let result = chain.locate(lat, lon, lang_code).await;
+        assert_eq!(result.address.as_deref(), Some(address));
+        assert_eq!((result.latitude, result.longitude), (lat, lon));
+    }
+
+    // the details of the match are kept along with the address
+    let result = chain.locate(55.7, 37.6, "en").await;
+    assert_eq!(result.provider, Some(Provider::Yandex));
+    assert_eq!(result.kind, PlaceKind::House);
+    assert_eq!(result.components.city.as_deref(), Some("Test City"));
+}
+
+#[test]
+fn test_google_geocode_response() {
+    let results = google::parse_geocode_response("geocode", include_bytes!("fixtures/google_geocode.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.address.as_deref(), Some("1600 Amphitheatre Pkwy, Mountain View, CA 94043, USA"));
+    assert_eq!((loc.latitude, loc.longitude), (37.4224428, -122.0842467));
+    assert_eq!(loc.place_id.as_deref(), Some("ChIJeRpOeF67j4AR9ydy_PIzPuM"));
+    assert_eq!(loc.kind, PlaceKind::House);
+    assert_eq!(loc.confidence, Some(1.0));
+    assert_eq!(loc.bbox.map(|bbox| (bbox.south, bbox.east)), Some((37.4212648197085, -122.0829089197085)));
+    assert_eq!(loc.components.street.as_deref(), Some("Amphitheatre Parkway"));
+    assert_eq!(loc.components.house.as_deref(), Some("1600"));
+    assert_eq!(loc.components.region.as_deref(), Some("California"));
+    assert_eq!(loc.components.postcode.as_deref(), Some("94043"));
+}
+
+#[test]
+fn test_google_geocode_error_status() {
+    let err = google::parse_geocode_response("geocode", include_bytes!("fixtures/google_geocode_denied.json")).unwrap_err();
+    assert_eq!(ErrorClass::of(&err), Some(ErrorClass::AuthFailure));
+    assert!(err.to_string().contains("The provided API key is invalid."));
+
+    let results = google::parse_geocode_response("geocode", br#"{"results": [], "status": "ZERO_RESULTS"}"#).unwrap();
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_google_text_search_response() {
+    let results = google::parse_text_search_response(include_bytes!("fixtures/google_text_search.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.address.as_deref(), Some("Googleplex, 1600 Amphitheatre Pkwy, Mountain View, CA 94043, USA"));
+    assert_eq!((loc.latitude, loc.longitude), (37.4220541, -122.0853242));
+    assert_eq!(loc.kind, PlaceKind::Poi);
+    assert_eq!(loc.components.name.as_deref(), Some("Googleplex"));
+    assert_eq!(loc.components.city.as_deref(), Some("Mountain View"));
+    assert!(loc.bbox.is_some());
+
+    let results = google::parse_text_search_response(b"{}").unwrap();
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_google_autocomplete_response() {
+    let results = google::parse_autocomplete_response(include_bytes!("fixtures/google_autocomplete.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.address.as_deref(), Some("Googleplex, Amphitheatre Parkway, Mountain View, CA, USA"));
+    assert_eq!(loc.distance, Some(1234.0));
+    assert_eq!(loc.kind, PlaceKind::Poi);
+
+    let suggestion = loc.as_suggestion().unwrap();
+    assert_eq!(suggestion.provider, Provider::Google);
+    assert_eq!(suggestion.place_id, "ChIJj61dQgK6j4AR4GeTYWZsKWw");
+
+    let results = google::parse_autocomplete_response(b"{}").unwrap();
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_google_place_details_response() {
+    let results = google::parse_place_details_response(include_bytes!("fixtures/google_place_details.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!((loc.latitude, loc.longitude), (37.4220541, -122.0853242));
+    assert!(loc.as_suggestion().is_none());
+}
+
+#[test]
+fn test_yandex_geocoder_response() {
+    let results = yandex::parse_geocoder_response("geocode", include_bytes!("fixtures/yandex_geocoder.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.address.as_deref(), Some("Россия, Москва, Тверская улица, 7"));
+    // the position is a "lon lat" string
+    assert_eq!((loc.latitude, loc.longitude), (55.757947, 37.611347));
+    assert_eq!(loc.kind, PlaceKind::House);
+    assert_eq!(loc.confidence, Some(1.0));
+    assert_eq!(loc.bbox, Some(BoundingBox { south: 55.755632, west: 37.607242, north: 55.760262, east: 37.615452 }));
+    assert_eq!(loc.components.region.as_deref(), Some("Москва"));
+    assert_eq!(loc.components.street.as_deref(), Some("Тверская улица"));
+    assert_eq!(loc.components.postcode.as_deref(), Some("125009"));
+}
+
+#[test]
+fn test_yandex_geocoder_metro_response() {
+    let results = yandex::parse_geocoder_response("geocode", include_bytes!("fixtures/yandex_geocoder_metro.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.kind, PlaceKind::Poi);
+    // the station isn't a part of the address, so it's taken as the name instead of being left out
+    assert_eq!(loc.components.name.as_deref(), Some("метро Тверская"));
+    assert_eq!(loc.components.city.as_deref(), Some("Москва"));
+    let formatted = formatter::format(loc, "ru").unwrap();
+    assert_eq!(formatted.title, "метро Тверская");
+}
+
+#[test]
+fn test_yandex_geocoder_malformed_position() {
+    let body = include_str!("fixtures/yandex_geocoder.json").replace("37.611347 55.757947", "37.611347");
+    let err = yandex::parse_geocoder_response("geocode", body.as_bytes()).unwrap_err();
+    assert!(err.is::<MalformedResponse>());
+    assert!(err.to_string().contains("response.GeoObjectCollection.featureMember[0].GeoObject.Point.pos"), "{err}");
+}
+
+#[test]
+fn test_yandex_places_response() {
+    let results = yandex::parse_places_response(include_bytes!("fixtures/yandex_places.json")).unwrap();
+    assert_eq!(results.len(), 2);
+
+    let company = &results[0];
+    assert_eq!(company.address.as_deref(), Some("Кафе Пушкинъ, Тверской бул., 26А, Москва, Россия"));
+    assert_eq!((company.latitude, company.longitude), (55.760879, 37.605524));
+    assert_eq!(company.place_id.as_deref(), Some("1018907821"));
+    assert_eq!(company.kind, PlaceKind::Poi);
+    assert_eq!(company.components.name.as_deref(), Some("Кафе Пушкинъ"));
+    assert_eq!(company.bbox, Some(BoundingBox { south: 55.758558, west: 37.601418, north: 55.763199, east: 37.60963 }));
+
+    let toponym = &results[1];
+    assert_eq!(toponym.address.as_deref(), Some("Москва"));
+    assert_eq!(toponym.kind, PlaceKind::City);
+}
+
+#[test]
+fn test_yandex_suggest_response() {
+    let results = yandex::parse_suggest_response(include_bytes!("fixtures/yandex_suggest.json")).unwrap();
+    assert_eq!(results.len(), 2);
+
+    let cafe = &results[0];
+    assert_eq!(cafe.address.as_deref(), Some("Кофемания, Россия, Москва, Большая Никитская улица, 13/6с1"));
+    assert_eq!(cafe.distance, Some(643.5));
+    assert_eq!(cafe.kind, PlaceKind::Poi);
+    let suggestion = cafe.as_suggestion().unwrap();
+    assert_eq!(suggestion.provider, Provider::Yandex);
+    assert_eq!(suggestion.place_id, "ymapsbm1://org?oid=1124715036");
+
+    let street = &results[1];
+    assert_eq!(street.address.as_deref(), Some("Кофейный переулок, Москва"));
+    assert_eq!(street.kind, PlaceKind::Street);
+    assert_eq!(street.distance, None);
+}
+
+#[test]
+fn test_nominatim_search_response() {
+    let results = osm::parse_search_response(include_bytes!("fixtures/nominatim_search.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.address.as_deref(), Some("Berlin, Deutschland"));
+    // the coordinates are strings
+    assert_eq!((loc.latitude, loc.longitude), (52.5170365, 13.3888599));
+    assert_eq!(loc.place_id.as_deref(), Some("relation/62422"));
+    assert_eq!(loc.kind, PlaceKind::City);
+    assert_eq!(loc.bbox, Some(BoundingBox { south: 52.3382448, west: 13.0883450, north: 52.6755087, east: 13.7611609 }));
+    assert_eq!(loc.components.city.as_deref(), Some("Berlin"));
+
+    let body = br#"[{"display_name": "Berlin Hauptbahnhof, Berlin, Deutschland", "lat": "52.5251", "lon": "13.3694",
+                    "category": "railway", "addresstype": "railway", "name": "Berlin Hauptbahnhof", "address": {"city": "Berlin"}}]"#;
+    let results = osm::parse_search_response(body).unwrap();
+    assert_eq!(results[0].kind, PlaceKind::Other);
+    assert_eq!(results[0].components.name.as_deref(), Some("Berlin Hauptbahnhof"));
+
+    let err = osm::parse_search_response(br#"[{"display_name": "Berlin", "lat": 52.5, "lon": "13.4"}]"#).unwrap_err();
+    assert!(err.to_string().contains("[0].lat"), "{err}");
+}
+
+#[test]
+fn test_nominatim_reverse_response() {
+    let results = osm::parse_reverse_response(include_bytes!("fixtures/nominatim_reverse.json")).unwrap();
+    assert_eq!(results.len(), 1);
+    let loc = &results[0];
+    assert_eq!(loc.place_id.as_deref(), Some("way/23733659"));
+    assert_eq!(loc.kind, PlaceKind::Poi);
+    assert_eq!(loc.components.name.as_deref(), Some("Reichstagsgebäude"));
+    assert_eq!(loc.components.street.as_deref(), Some("Platz der Republik"));
+    assert_eq!(loc.components.house.as_deref(), Some("1"));
+
+    let results = osm::parse_reverse_response(br#"{"error": "Unable to geocode"}"#).unwrap();
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_overpass_response() {
+    let results = overpass::parse_response(include_bytes!("fixtures/overpass_nearby.json"), "en", "pharmacy").unwrap();
+    assert_eq!(results.len(), 2);
+
+    let named = &results[0];
+    assert_eq!(named.address.as_deref(), Some("Pharmacy No. 1, Тверская улица, 1, Москва"));
+    assert_eq!((named.latitude, named.longitude), (55.7512, 37.6184));
+    assert_eq!(named.place_id.as_deref(), Some("node/1234567890"));
+    assert_eq!(named.kind, PlaceKind::Poi);
+
+    let unnamed = &results[1];
+    assert_eq!(unnamed.address.as_deref(), Some("pharmacy"));
+    assert_eq!((unnamed.latitude, unnamed.longitude), (55.7531, 37.6201));
+}
+
+#[test]
+fn test_overpass_query() {
+    let query = overpass::build_query(Category::Bar, (55.75, 37.62), 1500.0);
+    assert_eq!(query, r#"[out:json][timeout:10];(nwr["amenity"="bar"](around:1500,55.75,37.62);nwr["amenity"="pub"](around:1500,55.75,37.62););out center 20;"#);
+}
+
+#[test]
+fn test_nominatim_search_params() {
+    let params = osm::search_params("Red Square", None, Some(10_000.0));
+    assert_eq!(params, "q=Red%20Square&format=jsonv2&addressdetails=1&limit=10");
+
+    let params = osm::search_params("Red Square", Some((55.75, 37.62)), None);
+    assert!(params.contains("&viewbox="), "{params}");
+    assert!(!params.contains("&bounded=1") && !params.contains("&countrycodes="), "{params}");
+
+    // Strasbourg is close enough to the border with Germany
+    let params = osm::search_params("Rathaus", Some((48.5734, 7.7521)), Some(10_000.0));
+    assert!(params.contains("&bounded=1"), "{params}");
+    assert!(params.ends_with("&countrycodes=de,fr"), "{params}");
+}
+
 fn stub_finder(result: Vec<Location>) -> loc::LocFinderChainWrapper {
-    loc::finder("", StubLocFinder { result })
+    loc::finder("", StubLocFinder { result, delay: None, fail: false })
+}
+
+#[tokio::test]
+async fn test_stale_results() {
+    let stale_address = "123456 Stale Test Land";
+
+    let chain = SearchChain::new(vec![
+        failing_finder(),
+        loc::finder("", StaleStubLocFinder { result: vec![location(stale_address)], fail: true }),
+    ]);
+    let result = chain.find("", "en", None).await;
+    let addresses: Vec<String> = result.iter()
+        .filter_map(Location::address)
+        .collect();
+    assert_eq!(addresses, vec![stale_address]);
+
+    // nothing is served from the stale cache if the providers have answered, even with nothing
+    let chain = SearchChain::new(vec![
+        loc::finder("", StaleStubLocFinder { result: vec![location(stale_address)], fail: false }),
+    ]);
+    assert!(chain.find("", "en", None).await.is_empty());
+}
+
+fn failing_finder() -> loc::LocFinderChainWrapper {
+    loc::finder("", StubLocFinder { result: Vec::default(), delay: None, fail: true })
+}
+
+fn slow_finder(result: Vec<Location>) -> loc::LocFinderChainWrapper {
+    loc::finder("", StubLocFinder { result, delay: Some(Duration::from_secs(5)), fail: false })
 }
 
 fn location(address: &str) -> Location {
+    location_at(address, 100.0, 50.0)
+}
+
+fn location_at(address: &str, latitude: f64, longitude: f64) -> Location {
     Location {
         address: Some(address.to_string()),
-        latitude: 100.0,
-        longitude: 50.0,
+        ..Location::new(latitude, longitude)
     }
 }
 
 struct StubLocFinder {
-    result: Vec<Location>
+    result: Vec<Location>,
+    delay: Option<Duration>,
+    fail: bool,
 }
 
 #[async_trait]
 impl LocFinder for StubLocFinder {
     async fn find(&self, _: &str, _: &str, _: Option<(f64, f64)>) -> LocResult {
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        if self.fail {
+            return Err(anyhow!("stub failure"))
+        }
         Ok(self.result.clone())
     }
 }
+
+/// Returns the result only from the cache, like a provider that is down or doesn't know the place anymore.
+struct StaleStubLocFinder {
+    result: Vec<Location>,
+    fail: bool,
+}
+
+#[async_trait]
+impl LocFinder for StaleStubLocFinder {
+    async fn find(&self, _: &str, _: &str, _: Option<(f64, f64)>) -> LocResult {
+        if loc::cache::is_stale_lookup() {
+            Ok(self.result.clone())
+        } else if self.fail {
+            Err(anyhow!("stub failure"))
+        } else {
+            Ok(Vec::default())
+        }
+    }
+}
+
+#[async_trait]
+impl ReverseLocFinder for StubLocFinder {
+    async fn find_by_coords(&self, _: f64, _: f64, lang_code: &str) -> LocResult {
+        self.find("", lang_code, None).await
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use async_trait::async_trait;
+use http::Extensions;
+use mobc::Pool;
+use mobc_redis::{redis, RedisConnectionManager};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+const REDIS_KEY_PREFIX: &str = "throttle";
+
+/// Spaces the requests to an API by an interval, both within the process and across all replicas of the bot sharing
+/// the same Redis. It must be added after the cache, so that cached responses are not delayed.
+///
+/// If Redis is unavailable, the requests are throttled only within the process. Requests that would be queued for longer
+/// than `max_wait` are rejected with [`QueueFull`], so that the finder is skipped instead of holding up the chain.
+pub struct ThrottleMiddleware {
+    pool: Pool<RedisConnectionManager>,
+    key: String,
+    interval: Duration,
+    max_wait: Duration,
+    next_slot: Mutex<Instant>,
+    queued: AtomicU32,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("too many requests to {0} are queued")]
+pub struct QueueFull(String);
+
+impl ThrottleMiddleware {
+    pub fn new(pool: &Pool<RedisConnectionManager>, name: &str, interval: Duration, max_wait: Duration) -> ThrottleMiddleware {
+        ThrottleMiddleware {
+            pool: pool.clone(),
+            key: format!("{REDIS_KEY_PREFIX}:{name}"),
+            interval,
+            max_wait,
+            next_slot: Mutex::new(Instant::now()),
+            queued: AtomicU32::new(0),
+        }
+    }
+
+    /// Wait for a free slot. Concurrent callers of the process are queued behind the lock; the wait is estimated by
+    /// the number of callers ahead, since the slots taken by other replicas can't be known in advance.
+    pub(super) async fn wait(&self) -> Result<(), QueueFull> {
+        let queued = QueueGuard::enter(&self.queued);
+        if self.interval.saturating_mul(queued.ahead) > self.max_wait {
+            return Err(QueueFull(self.key.clone()))
+        }
+        let mut next_slot = self.next_slot.lock().await;
+        tokio::time::sleep_until(*next_slot).await;
+        loop {
+            match self.try_reserve().await {
+                Ok(None) => break,
+                Ok(Some(delay)) => tokio::time::sleep(delay).await,
+                Err(err) => {
+                    tracing::error!("couldn't reserve a slot for {} in Redis: {err}", self.key);
+                    break
+                }
+            }
+        }
+        *next_slot = Instant::now() + self.interval;
+        Ok(())
+    }
+
+    /// `None` if the slot is reserved, or the time until the current one is over if it's taken by another replica.
+    async fn try_reserve(&self) -> anyhow::Result<Option<Duration>> {
+        let mut conn = self.pool
+            .get().await?
+            .into_inner();
+        let (reserved, ttl): (Option<String>, i64) = redis::pipe().atomic()
+            .set_options(&self.key, 1, redis::SetOptions::default()
+                .conditional_set(redis::ExistenceCheck::NX)
+                .with_expiration(redis::SetExpiry::PX(self.interval.as_millis() as u64)))
+            .pttl(&self.key)
+            .query_async(&mut conn).await?;
+        // -2 means the key has just expired
+        Ok(reserved.is_none().then(|| Duration::from_millis(ttl.max(1) as u64)))
+    }
+}
+
+#[async_trait]
+impl Middleware for ThrottleMiddleware {
+    async fn handle(&self, req: Request, extensions: &mut Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+        if let Err(err) = self.wait().await {
+            // the cache turns the errors of the middlewares after it into strings
+            extensions.insert(err.clone());
+            return Err(reqwest_middleware::Error::middleware(err))
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// Restores the [`QueueFull`] error turned into a string by the cache. It must be added before the cache.
+pub struct QueueFullMiddleware;
+
+#[async_trait]
+impl Middleware for QueueFullMiddleware {
+    async fn handle(&self, req: Request, extensions: &mut Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+        let result = next.run(req, extensions).await;
+        match extensions.remove::<QueueFull>() {
+            Some(err) if result.is_err() => Err(reqwest_middleware::Error::middleware(err)),
+            _ => result,
+        }
+    }
+}
+
+pub fn is_queue_full(err: &anyhow::Error) -> bool {
+    err.is::<QueueFull>() || matches!(err.downcast_ref::<reqwest_middleware::Error>(),
+        Some(reqwest_middleware::Error::Middleware(err)) if err.is::<QueueFull>())
+}
+
+/// Counts the callers waiting for a slot, including the one that's being served; cancelled callers leave the queue too.
+struct QueueGuard<'a> {
+    queued: &'a AtomicU32,
+    ahead: u32,
+}
+
+impl<'a> QueueGuard<'a> {
+    fn enter(queued: &'a AtomicU32) -> QueueGuard<'a> {
+        let ahead = queued.fetch_add(1, Ordering::SeqCst);
+        QueueGuard { queued, ahead }
+    }
+}
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
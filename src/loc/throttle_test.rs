@@ -0,0 +1,37 @@
+use std::time::Duration;
+use tokio::time::Instant;
+use super::throttle::ThrottleMiddleware;
+use crate::testutils::start_redis;
+
+#[tokio::test]
+async fn test_throttle() {
+    let (_redis_container, redis_pool) = start_redis().await;
+    let interval = Duration::from_millis(200);
+    // two replicas sharing the same Redis
+    let first = ThrottleMiddleware::new(&redis_pool, "test", interval, Duration::MAX);
+    let second = ThrottleMiddleware::new(&redis_pool, "test", interval, Duration::MAX);
+
+    let start = Instant::now();
+    first.wait().await.unwrap();
+    second.wait().await.unwrap();
+    first.wait().await.unwrap();
+    assert!(start.elapsed() >= interval * 2, "{:?}", start.elapsed());
+
+    let other = ThrottleMiddleware::new(&redis_pool, "other", interval, Duration::MAX);
+    let start = Instant::now();
+    other.wait().await.unwrap();
+    assert!(start.elapsed() < interval, "other APIs must not be affected");
+}
+
+#[tokio::test]
+async fn test_queue_is_bounded() {
+    let (_redis_container, redis_pool) = start_redis().await;
+    let interval = Duration::from_millis(200);
+    let throttle = ThrottleMiddleware::new(&redis_pool, "bounded", interval, interval + interval / 2);
+
+    let (first, second, third, fourth) = tokio::join!(throttle.wait(), throttle.wait(), throttle.wait(), throttle.wait());
+    assert!(first.is_ok() && second.is_ok());
+    assert!(third.is_err() && fourth.is_err(), "requests waiting longer than the maximum must be rejected");
+
+    throttle.wait().await.expect("the queue must be released");
+}
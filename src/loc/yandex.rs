@@ -1,85 +1,128 @@
-use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 use anyhow::anyhow;
 use async_trait::async_trait;
-use once_cell::sync::Lazy;
 use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Deserializer};
 use strum_macros::EnumString;
+use super::budget::{is_budget_exceeded, ApiBudget};
 use super::cache::WithCachedResponseCounters;
-use super::{cache, get_bounds, Location, LocFinder, LocResult, SEARCH_RADIUS, SearchParams};
+use super::credentials::ApiKey;
+use super::errors::{check_response, ErrorClass};
+use super::keys::KeyPool;
+use super::nearby::{self, Category, NearbyLocFinder};
+use super::reverse::ReverseLocFinder;
+use super::suggest::{Suggestion, SuggestionResolver};
+use super::{cache, geo, response, get_bounds, AddressComponents, BoundingBox, Location, LocFinder, LocResult, PlaceKind, Provider, SEARCH_RADIUS, SearchParams};
 use crate::metrics;
 use crate::redis::REDIS;
 
 const GEOCODER_ENV_API_KEY: &str = "YANDEX_MAPS_GEOCODER_API_KEY";
 const PLACES_ENV_API_KEY: &str   = "YANDEX_MAPS_PLACES_API_KEY";
+const SUGGEST_ENV_API_KEY: &str  = "YANDEX_MAPS_SUGGEST_API_KEY";
+/// Replaced by the mode in the search chain config.
+const OBSOLETE_ENV_MODE: &str = "YAPI_MODE";
 
-pub static YAPI_MODE: Lazy<YandexAPIMode> = Lazy::new(|| {
-    let val = std::env::var("YAPI_MODE").expect("YAPI_MODE must be set!");
-    tracing::info!("YAPI_MODE is {val}");
-    YandexAPIMode::from_str(val.as_str()).expect("Invalid value of YAPI_MODE")
-});
-
-#[derive(EnumString)]
+#[derive(EnumString, Debug, Default, Copy, Clone)]
+#[strum(ascii_case_insensitive)]
 pub enum YandexAPIMode {
+    #[default]
     Geocode,    // HTTP Geocoder request
     Place,      // Places API request
     GeoPlace,   // Geocoder request first, Places if nothing was found
-}
-
-/// Load and check required parameters at startup
-pub fn preload_env_vars() {
-    let _ = *YAPI_MODE;
+    Suggest,    // Geosuggest request; the Geocoder is requested only for the chosen suggestion
 }
 
 pub struct YandexLocFinder {
     client: ClientWithMiddleware,
+    mode: RwLock<YandexAPIMode>,
 
-    geocode_api_key: String,
-    places_api_key: Option<String>,
+    geocode_keys: KeyPool,
+    places_keys: Option<KeyPool>,
+    suggest_keys: Option<KeyPool>,
 
     geocode_req_counter: prometheus::Counter,
+    reverse_geocode_req_counter: prometheus::Counter,
     place_req_counter: prometheus::Counter,
+    suggest_req_counter: prometheus::Counter,
     cached_resp_counter: prometheus::Counter,
     fetched_resp_counter: prometheus::Counter,
+
+    geocode_budget: Arc<ApiBudget>,
+    reverse_geocode_budget: Arc<ApiBudget>,
+    place_budget: Arc<ApiBudget>,
+    suggest_budget: Arc<ApiBudget>,
 }
 
 impl YandexLocFinder {
-    pub fn init(geocode_api_key: String, places_api_key: Option<String>) -> YandexLocFinder {
+    pub fn init(geocode_keys: KeyPool, places_keys: Option<KeyPool>, suggest_keys: Option<KeyPool>) -> YandexLocFinder {
         let base_opts = prometheus::Opts::new("yandex_maps_api_requests_total", "count of requests to the Yandex Maps API");
         let geocode_opts = base_opts.clone().const_label("API", "geocode");
+        let reverse_geocode_opts = base_opts.clone().const_label("API", "reverse-geocode");
         let place_opts   = base_opts.clone().const_label("API", "place");
+        let suggest_opts = base_opts.clone().const_label("API", "suggest");
 
         let resp_opts = prometheus::Opts::new("yandex_maps_api_responses_total", "count of responses from the Yandex Maps API split by the source");
         let from_cache_opts = resp_opts.clone().const_label("source", "cache");
         let from_remote_opts = resp_opts.const_label("source", "remote");
 
         YandexLocFinder {
-            client: cache::caching_client(&REDIS.pool),
+            client: cache::caching_client(Provider::Yandex, &REDIS.pool),
+            mode: RwLock::default(),
 
-            geocode_api_key,
-            places_api_key,
+            geocode_keys,
+            places_keys,
+            suggest_keys,
 
             geocode_req_counter:  metrics::REGISTRY.register_counter("Yandex Maps API (geocode) requests", geocode_opts),
+            reverse_geocode_req_counter: metrics::REGISTRY.register_counter("Yandex Maps API (reverse geocode) requests", reverse_geocode_opts),
             place_req_counter:    metrics::REGISTRY.register_counter("Yandex Maps API (place) requests", place_opts),
+            suggest_req_counter:  metrics::REGISTRY.register_counter("Yandex Maps API (suggest) requests", suggest_opts),
             cached_resp_counter:  metrics::REGISTRY.register_counter("Yandex Maps API requests", from_cache_opts),
             fetched_resp_counter: metrics::REGISTRY.register_counter("Yandex Maps API requests", from_remote_opts),
+
+            geocode_budget:         Arc::new(ApiBudget::from_env(&REDIS.pool, "yandex", "geocode", "yandex_maps_api")),
+            reverse_geocode_budget: Arc::new(ApiBudget::from_env(&REDIS.pool, "yandex", "reverse-geocode", "yandex_maps_api")),
+            place_budget:           Arc::new(ApiBudget::from_env(&REDIS.pool, "yandex", "place", "yandex_maps_api")),
+            suggest_budget:         Arc::new(ApiBudget::from_env(&REDIS.pool, "yandex", "suggest", "yandex_maps_api")),
         }
     }
 
     pub fn from_env() -> YandexLocFinder {
-        let geocode_api_key = std::env::var(GEOCODER_ENV_API_KEY).expect("Yandex Maps Geocoder API key is required!");
-        let places_api_key = match *YAPI_MODE {
-            YandexAPIMode::Place | YandexAPIMode::GeoPlace => {
-                let api_key = std::env::var(PLACES_ENV_API_KEY).expect("Yandex Maps Places API key is required!");
-                Some(api_key)
-            }
-            YandexAPIMode::Geocode => None
+        let geocode_keys = KeyPool::from_env(Provider::Yandex, GEOCODER_ENV_API_KEY).expect("Yandex Maps Geocoder API key is required!");
+        let places_keys = KeyPool::from_env(Provider::Yandex, PLACES_ENV_API_KEY);
+        let suggest_keys = KeyPool::from_env(Provider::Yandex, SUGGEST_ENV_API_KEY);
+        if std::env::var(OBSOLETE_ENV_MODE).is_ok() {
+            tracing::error!("{OBSOLETE_ENV_MODE} is ignored, set the mode of the yandex finder in the search chain config instead");
+        }
+        Self::init(geocode_keys, places_keys, suggest_keys)
+    }
+
+    /// Switch the mode, which is set by the search chain config and can be changed by its reload.
+    /// The modes using Places API or Geosuggest API fall back to the Geocoder if there is no key for it.
+    pub fn set_mode(&self, mode: YandexAPIMode) {
+        let needs_places_api = matches!(mode, YandexAPIMode::Place | YandexAPIMode::GeoPlace);
+        let mode = if needs_places_api && self.places_keys.is_none() {
+            tracing::warn!("{PLACES_ENV_API_KEY} is required for the {mode:?} mode of Yandex Maps API, the Geocoder is used instead");
+            YandexAPIMode::Geocode
+        } else if matches!(mode, YandexAPIMode::Suggest) && self.suggest_keys.is_none() {
+            tracing::warn!("{SUGGEST_ENV_API_KEY} is required for the {mode:?} mode of Yandex Maps API, the Geocoder is used instead");
+            YandexAPIMode::Geocode
+        } else {
+            mode
         };
-        Self::init(geocode_api_key, places_api_key)
+        tracing::info!("The mode of Yandex Maps API is {mode:?}");
+        *self.mode.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = mode;
     }
 
     #[tracing::instrument(skip(self))]
     async fn find_geo_place(&self, address: &str, params: SearchParams<'_>) -> LocResult {
-        let mut results = self.find_geo(address, params).await?;
+        let mut results = match self.find_geo(address, params).await {
+            Err(err) if is_budget_exceeded(&err) || ErrorClass::of(&err) == Some(ErrorClass::QuotaExceeded) => {
+                tracing::warn!("{err}, falling back to Places API");
+                Vec::default()
+            },
+            res => res?
+        };
         if results.is_empty() {
             results = self.find_place(address, params).await?;
         }
@@ -91,48 +134,87 @@ impl YandexLocFinder {
         self.geocode_req_counter.inc();
 
         let encoded_address = urlencoding::encode(address);
-        let url = format!("https://geocode-maps.yandex.ru/1.x?apikey={}&lang={}&geocode={}&format=json{}",
-                          self.geocode_api_key, params.lang_code, encoded_address, build_bbox_part(params.location));
-        let resp = self.client.get(url).send().await?;
-        self.inc_resp_counter(&resp);
-
-        let json = resp.json::<serde_json::Value>().await?;
-        tracing::info!("Response from Yandex Maps Geocoder: {json}");
-
-        let empty: Vec<serde_json::Value> = Vec::new();
-        let result = json["response"]["GeoObjectCollection"]["featureMember"]
-            .as_array()
-            .unwrap_or(&empty)
-            .iter()
-            .filter_map(geocode_elem_mapper)
-            .collect();
-        Ok(result)
+        let url = &format!("https://geocode-maps.yandex.ru/1.x?lang={}&geocode={}&format=json{}",
+                          params.lang_code, encoded_address, build_bbox_part(params.location));
+        self.geocode_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.geocode_budget.clone())
+                .with_extension(ApiKey::QueryParam("apikey", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Yandex, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Yandex Maps Geocoder: {}", String::from_utf8_lossy(&body));
+            parse_geocoder_response("geocode", &body)
+        }).await
     }
 
     #[tracing::instrument(skip(self))]
     async fn find_place(&self, address: &str, params: SearchParams<'_>) -> LocResult {
         self.place_req_counter.inc();
 
-        let api_key = self.places_api_key.clone()
+        let places_keys = self.places_keys.as_ref()
             .ok_or(anyhow!("unexpected absence of a key for Yandex Maps Places API"))?;
 
         let encoded_address = urlencoding::encode(address);
-        let url = format!("https://search-maps.yandex.ru/v1/?apikey={}&lang={}&text={}{}",
-                          api_key, params.lang_code, encoded_address, build_bbox_part(params.location));
-        let resp = self.client.get(url).send().await?;
-        self.inc_resp_counter(&resp);
+        let url = &format!("https://search-maps.yandex.ru/v1/?lang={}&text={}{}",
+                          params.lang_code, encoded_address, build_bbox_part(params.location));
+        places_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.place_budget.clone())
+                .with_extension(ApiKey::QueryParam("apikey", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Yandex, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Yandex Maps Places API: {}", String::from_utf8_lossy(&body));
+            parse_places_response(&body)
+        }).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_suggestions(&self, text: &str, params: SearchParams<'_>) -> LocResult {
+        self.suggest_req_counter.inc();
+
+        let suggest_keys = self.suggest_keys.as_ref()
+            .ok_or(anyhow!("unexpected absence of a key for Yandex Maps Geosuggest API"))?;
+
+        let encoded_text = urlencoding::encode(text);
+        let url = &format!("https://suggest-maps.yandex.ru/v1/suggest?lang={}&text={}&attrs=uri&print_address=1{}",
+                          params.lang_code, encoded_text, build_ll_spn_part(params.location));
+        suggest_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.suggest_budget.clone())
+                .with_extension(ApiKey::QueryParam("apikey", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Yandex, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Yandex Maps Geosuggest API: {}", String::from_utf8_lossy(&body));
+            parse_suggest_response(&body)
+        }).await
+    }
 
-        let json = resp.json::<serde_json::Value>().await?;
-        tracing::info!("Response from Yandex Maps Places API: {json}");
+    #[tracing::instrument(skip(self))]
+    async fn find_geo_by_uri(&self, uri: &str, lang_code: &str) -> LocResult {
+        self.geocode_req_counter.inc();
 
-        let empty: Vec<serde_json::Value> = Vec::new();
-        let result = json["features"]
-            .as_array()
-            .unwrap_or(&empty)
-            .iter()
-            .filter_map(places_elem_mapper)
-            .collect();
-        Ok(result)
+        let url = &format!("https://geocode-maps.yandex.ru/1.x?lang={lang_code}&uri={}&format=json", urlencoding::encode(uri));
+        self.geocode_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.geocode_budget.clone())
+                .with_extension(ApiKey::QueryParam("apikey", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Yandex, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Yandex Maps Geocoder: {}", String::from_utf8_lossy(&body));
+            parse_geocoder_response("geocode", &body)
+        }).await
     }
 }
 
@@ -141,11 +223,80 @@ impl LocFinder for YandexLocFinder {
     #[tracing::instrument(skip(self))]
     async fn find(&self, query: &str, lang_code: &str, location: Option<(f64, f64)>) -> LocResult {
         let params = SearchParams { lang_code, location };
-        match *YAPI_MODE {
+        let mode = *self.mode.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match mode {
             YandexAPIMode::Geocode => self.find_geo(query, params).await,
             YandexAPIMode::Place => self.find_place(query, params).await,
             YandexAPIMode::GeoPlace => self.find_geo_place(query, params).await,
+            YandexAPIMode::Suggest => self.find_suggestions(query, params).await,
+        }
+    }
+}
+
+#[async_trait]
+impl SuggestionResolver for YandexLocFinder {
+    /// Geocode the suggestion by its URI, or by its text if the Geocoder doesn't know the URI.
+    #[tracing::instrument(skip(self))]
+    async fn resolve(&self, suggestion: &Suggestion, lang_code: &str) -> LocResult {
+        let results = self.find_geo_by_uri(&suggestion.place_id, lang_code).await?;
+        if !results.is_empty() {
+            return Ok(results)
         }
+        let params = SearchParams { lang_code, location: None };
+        self.find_geo(&suggestion.text, params).await
+    }
+}
+
+#[async_trait]
+impl NearbyLocFinder for YandexLocFinder {
+    /// Organizations are searched by the query within the area, since Places API has no categories of its own.
+    /// Nothing is found without a key for Places API, so that the next finder of the chain is used.
+    #[tracing::instrument(skip(self))]
+    async fn find_nearby(&self, _category: Category, query: &str, lang_code: &str, location: (f64, f64)) -> LocResult {
+        let Some(places_keys) = self.places_keys.as_ref() else {
+            tracing::debug!("{PLACES_ENV_API_KEY} is not set, the nearby search is skipped");
+            return Ok(Vec::default())
+        };
+        self.place_req_counter.inc();
+
+        let bbox = geo::bounding_box(location, nearby::radius());
+        let (lat, lng) = location;
+        let url = &format!("https://search-maps.yandex.ru/v1/?lang={lang_code}&text={}&type=biz&ll={lng},{lat}&spn={},{}&rspn=1&results=20",
+                          urlencoding::encode(query), bbox.east - bbox.west, bbox.north - bbox.south);
+        places_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.place_budget.clone())
+                .with_extension(ApiKey::QueryParam("apikey", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Yandex, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Yandex Maps Places API: {}", String::from_utf8_lossy(&body));
+            parse_places_response(&body)
+        }).await
+    }
+}
+
+#[async_trait]
+impl ReverseLocFinder for YandexLocFinder {
+    #[tracing::instrument(skip(self))]
+    async fn find_by_coords(&self, latitude: f64, longitude: f64, lang_code: &str) -> LocResult {
+        self.reverse_geocode_req_counter.inc();
+
+        let url = &format!("https://geocode-maps.yandex.ru/1.x?lang={lang_code}&geocode={longitude},{latitude}&format=json");
+        self.geocode_keys.with_key(|key| async move {
+            let resp = self.client.get(url)
+                .with_extension(self.reverse_geocode_budget.clone())
+                .with_extension(ApiKey::QueryParam("apikey", key))
+                .send().await?;
+            self.inc_resp_counter(&resp);
+            let resp = check_response(Provider::Yandex, resp).await?;
+
+            let body = resp.bytes().await?;
+            tracing::info!("Response from Yandex Maps Geocoder: {}", String::from_utf8_lossy(&body));
+            parse_geocoder_response("reverse-geocode", &body)
+        }).await
     }
 }
 
@@ -159,38 +310,337 @@ impl WithCachedResponseCounters for YandexLocFinder {
     }
 }
 
-fn geocode_elem_mapper(v: &serde_json::Value) -> Option<Location> {
-    let obj = &v["GeoObject"];
-    let metadata = &obj["metaDataProperty"]["GeocoderMetaData"];
-    let address = Some(metadata["text"].as_str()?.to_string());
+#[derive(Deserialize)]
+struct GeocoderResponse {
+    response: GeocoderResponseBody,
+}
+
+#[derive(Deserialize)]
+struct GeocoderResponseBody {
+    #[serde(rename = "GeoObjectCollection")]
+    geo_object_collection: GeoObjectCollection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeoObjectCollection {
+    #[serde(default)]
+    feature_member: Vec<FeatureMember>,
+}
+
+#[derive(Deserialize)]
+struct FeatureMember {
+    #[serde(rename = "GeoObject")]
+    geo_object: GeoObject,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeoObject {
+    meta_data_property: MetaDataProperty,
+    #[serde(rename = "Point")]
+    point: Point,
+    bounded_by: Option<BoundedBy>,
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MetaDataProperty {
+    #[serde(rename = "GeocoderMetaData")]
+    geocoder_meta_data: GeocoderMetaData,
+}
+
+#[derive(Deserialize)]
+struct GeocoderMetaData {
+    text: String,
+    kind: Option<String>,
+    precision: Option<String>,
+    #[serde(rename = "Address")]
+    address: Option<Address>,
+}
+
+#[derive(Deserialize)]
+struct Address {
+    postal_code: Option<String>,
+    #[serde(rename = "Components", default)]
+    components: Vec<AddressComponent>,
+}
+
+#[derive(Deserialize)]
+struct AddressComponent {
+    kind: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Point {
+    pos: Position,
+}
+
+#[derive(Deserialize)]
+struct BoundedBy {
+    #[serde(rename = "Envelope")]
+    envelope: Envelope,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    lower_corner: Position,
+    upper_corner: Position,
+}
+
+/// The Geocoder returns positions as strings of the form "{longitude} {latitude}".
+struct Position {
+    longitude: f64,
+    latitude: f64,
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pos = String::deserialize(deserializer)?;
+        let (longitude, latitude) = pos.split_once(' ')
+            .ok_or_else(|| serde::de::Error::custom(format!("'{pos}' is not a pair of coordinates")))?;
+        Ok(Position {
+            longitude: longitude.parse().map_err(serde::de::Error::custom)?,
+            latitude: latitude.parse().map_err(serde::de::Error::custom)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct PlacesResponse {
+    #[serde(default)]
+    features: Vec<PlacesFeature>,
+}
+
+#[derive(Deserialize)]
+struct PlacesFeature {
+    geometry: PlacesGeometry,
+    properties: PlacesProperties,
+}
+
+#[derive(Deserialize)]
+struct PlacesGeometry {
+    /// `[longitude, latitude]`
+    coordinates: (f64, f64),
+}
+
+#[derive(Deserialize)]
+struct PlacesProperties {
+    name: String,
+    description: Option<String>,
+    /// The lower and the upper corners as `[longitude, latitude]` pairs.
+    #[serde(rename = "boundedBy")]
+    bounded_by: Option<[(f64, f64); 2]>,
+    // organizations have CompanyMetaData, toponyms have GeocoderMetaData
+    #[serde(rename = "CompanyMetaData")]
+    company_meta_data: Option<CompanyMetaData>,
+    #[serde(rename = "GeocoderMetaData")]
+    geocoder_meta_data: Option<ToponymMetaData>,
+}
+
+#[derive(Deserialize)]
+struct CompanyMetaData {
+    id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ToponymMetaData {
+    kind: Option<String>,
+    #[serde(rename = "Address")]
+    address: Option<Address>,
+}
 
-    let pos = &obj["Point"]["pos"].as_str()?
-        .split(' ')
-        .collect::<Vec<&str>>();
-    if pos.len() < 2 {
-        tracing::error!("pos length < 2: {pos:?}");
-        return None
+#[derive(Deserialize)]
+struct SuggestResponse {
+    #[serde(default)]
+    results: Vec<SuggestResult>,
+}
+
+#[derive(Deserialize)]
+struct SuggestResult {
+    title: SuggestText,
+    subtitle: Option<SuggestText>,
+    #[serde(default)]
+    tags: Vec<String>,
+    distance: Option<SuggestDistance>,
+    address: Option<SuggestAddress>,
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SuggestText {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct SuggestDistance {
+    /// In meters; returned only if `ll` is passed.
+    value: f64,
+}
+
+#[derive(Deserialize)]
+struct SuggestAddress {
+    formatted_address: Option<String>,
+}
+
+/// Used for both the direct and the reverse geocoding.
+pub(super) fn parse_geocoder_response(api: &'static str, body: &[u8]) -> LocResult {
+    let resp: GeocoderResponse = response::parse(Provider::Yandex, api, body)?;
+    let results = resp.response.geo_object_collection.feature_member.into_iter()
+        .map(|member| map_geo_object(member.geo_object))
+        .collect();
+    Ok(results)
+}
+
+pub(super) fn parse_places_response(body: &[u8]) -> LocResult {
+    let resp: PlacesResponse = response::parse(Provider::Yandex, "place", body)?;
+    let results = resp.features.into_iter()
+        .map(map_places_feature)
+        .collect();
+    Ok(results)
+}
+
+/// Results without a URI are skipped, since they couldn't be resolved unambiguously.
+pub(super) fn parse_suggest_response(body: &[u8]) -> LocResult {
+    let resp: SuggestResponse = response::parse(Provider::Yandex, "suggest", body)?;
+    let results = resp.results.into_iter()
+        .filter_map(map_suggest_result)
+        .collect();
+    Ok(results)
+}
+
+fn map_geo_object(obj: GeoObject) -> Location {
+    let metadata = obj.meta_data_property.geocoder_meta_data;
+    Location {
+        address: Some(metadata.text),
+        provider: Some(Provider::Yandex),
+        place_id: obj.uri,
+        kind: metadata.kind.as_deref().map(map_kind).unwrap_or_default(),
+        confidence: metadata.precision.as_deref().and_then(map_precision),
+        bbox: obj.bounded_by.map(|bounded_by| BoundingBox {
+            south: bounded_by.envelope.lower_corner.latitude,
+            west: bounded_by.envelope.lower_corner.longitude,
+            north: bounded_by.envelope.upper_corner.latitude,
+            east: bounded_by.envelope.upper_corner.longitude,
+        }),
+        components: metadata.address.map(|address| map_address(address, metadata.kind.as_deref())).unwrap_or_default(),
+        ..Location::new(obj.point.pos.latitude, obj.point.pos.longitude)
     }
-    let longitude: f64 = pos[0].parse().ok()?;
-    let latitude: f64 = pos[1].parse().ok()?;
+}
 
+fn map_places_feature(feature: PlacesFeature) -> Location {
+    let props = feature.properties;
+    let address = match &props.description {
+        Some(description) => format!("{}, {}", props.name, description),
+        None => props.name.clone(),
+    };
+
+    let (place_id, kind, components) = match (props.company_meta_data, props.geocoder_meta_data) {
+        (Some(company), _) => {
+            let components = AddressComponents {
+                name: Some(props.name),
+                ..AddressComponents::default()
+            };
+            (company.id, PlaceKind::Poi, components)
+        }
+        (None, Some(metadata)) => {
+            let kind = metadata.kind.as_deref().map(map_kind).unwrap_or_default();
+            (None, kind, metadata.address.map(|address| map_address(address, metadata.kind.as_deref())).unwrap_or_default())
+        }
+        (None, None) => (None, PlaceKind::Other, AddressComponents::default()),
+    };
+
+    let (longitude, latitude) = feature.geometry.coordinates;
+    Location {
+        address: Some(address),
+        provider: Some(Provider::Yandex),
+        place_id,
+        kind,
+        bbox: props.bounded_by.map(|[(west, south), (east, north)]| BoundingBox { south, west, north, east }),
+        components,
+        ..Location::new(latitude, longitude)
+    }
+}
+
+fn map_suggest_result(result: SuggestResult) -> Option<Location> {
+    let address = match (result.address.and_then(|address| address.formatted_address), result.subtitle) {
+        // the formatted address of organizations lacks their names
+        (Some(address), _) if result.tags.iter().any(|tag| tag == "business") => format!("{}, {}", result.title.text, address),
+        (Some(address), _) => address,
+        (None, Some(subtitle)) => format!("{}, {}", result.title.text, subtitle.text),
+        (None, None) => result.title.text,
+    };
+    let kind = result.tags.iter()
+        .map(|tag| match tag.as_str() {
+            "business" => PlaceKind::Poi,
+            tag => map_kind(tag),
+        })
+        .find(|kind| *kind != PlaceKind::Other)
+        .unwrap_or_default();
     Some(Location {
-        address, latitude, longitude
+        address: Some(address),
+        distance: result.distance.map(|distance| distance.value),
+        provider: Some(Provider::Yandex),
+        place_id: Some(result.uri?),
+        kind,
+        suggestion: true,
+        ..Location::new(0.0, 0.0)
     })
 }
 
-fn places_elem_mapper(v: &serde_json::Value) -> Option<Location> {
-    let name = v["properties"]["name"].as_str()?;
-    let description = v["properties"]["description"].as_str()?;
-    let address = Some(format!("{}, {}", name, description));
+fn map_kind(kind: &str) -> PlaceKind {
+    match kind {
+        "house" => PlaceKind::House,
+        "street" => PlaceKind::Street,
+        "locality" => PlaceKind::City,
+        "province" | "area" => PlaceKind::Region,
+        "country" => PlaceKind::Country,
+        "metro" | "railway_station" | "airport" | "entrance" => PlaceKind::Poi,
+        _ => PlaceKind::Other
+    }
+}
 
-    let loc = &v["geometry"]["coordinates"];
-    let longitude: f64 = loc[0].as_f64()?;
-    let latitude: f64 = loc[1].as_f64()?;
+fn map_precision(precision: &str) -> Option<f64> {
+    match precision {
+        "exact" => Some(1.0),
+        "number" => Some(0.9),
+        "near" => Some(0.7),
+        "range" => Some(0.6),
+        "street" => Some(0.5),
+        "other" => Some(0.3),
+        _ => None
+    }
+}
 
-    Some(Location {
-        address, latitude, longitude
-    })
+/// Objects that aren't parts of an address, like metro stations or districts, are named by the component of their own kind.
+fn map_address(address: Address, kind: Option<&str>) -> AddressComponents {
+    let mut components = AddressComponents {
+        postcode: address.postal_code,
+        ..AddressComponents::default()
+    };
+    for component in address.components {
+        let name = component.name;
+        match component.kind.as_str() {
+            "country" => components.country = Some(name),
+            // provinces go from the federal district to the subject, so the last one is kept
+            "province" => components.region = Some(name),
+            "locality" => { components.city.get_or_insert(name); },
+            "street" => components.street = Some(name),
+            "house" => components.house = Some(name),
+            other if kind == Some(other) => components.name = Some(name),
+            _ => {}
+        }
+    }
+    components
+}
+
+/// Geosuggest is biased by the center and the span of the area instead of a bounding box.
+fn build_ll_spn_part(location: Option<(f64, f64)>) -> String {
+    location
+        .map(|(lat, lng)| format!("&ll={lng},{lat}&spn={span},{span}", span = *SEARCH_RADIUS * 2.0))
+        .unwrap_or_default()
 }
 
 fn build_bbox_part(location: Option<(f64, f64)>) -> String {
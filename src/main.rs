@@ -26,6 +26,7 @@ use teloxide::dispatching::dialogue::serializer::Json;
 use teloxide::dptree::deps;
 use teloxide::prelude::*;
 use teloxide::update_listeners::webhooks::{axum_to_router, Options};
+use tokio::signal::unix::{signal, SignalKind};
 use crate::handlers::options::CancellationCallbackData;
 use crate::handlers::options::location::LocationState;
 use crate::redis::REDIS;
@@ -45,6 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let tracer_provider = observability::init_tracing()?;
     handlers::preload_env_vars();
+    tokio::spawn(reload_finders_on_hangup());
 
     let handler = dptree::entry()
         .branch(Update::filter_inline_query().endpoint(handlers::inline_handler))
@@ -179,3 +181,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracer_provider.shutdown()?;
     result
 }
+
+/// `kill -HUP` makes the bot re-read the search chain config without a restart.
+async fn reload_finders_on_hangup() {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            tracing::error!("couldn't install the SIGHUP handler: {err}");
+            return
+        }
+    };
+    while hangup.recv().await.is_some() {
+        tracing::info!("Got SIGHUP, reloading the search chain config");
+        handlers::reload_finders();
+    }
+}
@@ -117,6 +117,24 @@ impl Registry {
         c
     }
 
+    /// Register a family of counters distinguished by the values of `labels`.
+    pub fn register_counter_vec(&self, name: &str, opts: Opts, labels: &[&str]) -> prometheus::CounterVec {
+        let c = prometheus::CounterVec::new(opts, labels)
+            .unwrap_or_else(|_| panic!("unable to create {name} counter"));
+        self.0.register(Box::new(c.clone()))
+            .unwrap_or_else(|_| panic!("unable to register the {name} counter"));
+        c
+    }
+
+    /// Register a family of gauges distinguished by the values of `labels`.
+    pub fn register_gauge_vec(&self, name: &str, opts: Opts, labels: &[&str]) -> prometheus::GaugeVec {
+        let g = prometheus::GaugeVec::new(opts, labels)
+            .unwrap_or_else(|_| panic!("unable to create {name} gauge"));
+        self.0.register(Box::new(g.clone()))
+            .unwrap_or_else(|_| panic!("unable to register the {name} gauge"));
+        g
+    }
+
     fn register(&self, counter: &Counter) -> &Self {
         self.0.register(Box::new(counter.inner.clone()))
             .unwrap_or_else(|_| panic!("unable to register the {} counter", counter.name));